@@ -0,0 +1,262 @@
+// anvil.rs
+// Importa bloques de un mundo guardado (formato Anvil, archivos region/r.X.Z.mca)
+// dentro de una caja dada en coordenadas de bloque. Solo chunks 1.18+
+// (lista `sections` con `block_states` paletizados).
+use raylib::prelude::*;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::import::{self, ImportError, ImportReport};
+use crate::materials::MaterialLibrary;
+use crate::nbt::{self, Tag};
+use crate::scene::Scene;
+
+// Límite para no reservar memoria de más con cajas gigantes
+const MAX_VOLUME: usize = 1 << 24;
+
+pub struct AnvilOptions {
+    // Esquinas de la caja en coordenadas de bloque del mundo (ambas incluidas)
+    pub min: [i32; 3],
+    pub max: [i32; 3],
+    // Dónde queda la esquina `min` en la escena
+    pub origin: Vector3,
+}
+
+// Bloques de la caja: índice en `names` por cada posición (0 = aire o sin cargar)
+struct BlockBox {
+    min: [i32; 3],
+    size: [usize; 3],
+    cells: Vec<u16>,
+    names: Vec<String>,
+    ids: HashMap<String, u16>,
+}
+
+impl BlockBox {
+    fn index(&self, x: i32, y: i32, z: i32) -> Option<usize> {
+        let (dx, dy, dz) = (x - self.min[0], y - self.min[1], z - self.min[2]);
+        if dx < 0 || dy < 0 || dz < 0 {
+            return None;
+        }
+        let (dx, dy, dz) = (dx as usize, dy as usize, dz as usize);
+        if dx >= self.size[0] || dy >= self.size[1] || dz >= self.size[2] {
+            return None;
+        }
+        Some((dy * self.size[2] + dz) * self.size[0] + dx)
+    }
+
+    fn set(&mut self, x: i32, y: i32, z: i32, name: &str) -> Result<(), ImportError> {
+        let Some(index) = self.index(x, y, z) else { return Ok(()) };
+        if import::is_air(name) {
+            return Ok(());
+        }
+        let id = match self.ids.get(name) {
+            Some(id) => *id,
+            None => {
+                if self.names.len() >= u16::MAX as usize {
+                    return Err(ImportError("demasiados tipos de bloque distintos".to_string()));
+                }
+                let id = self.names.len() as u16;
+                self.names.push(name.to_string());
+                self.ids.insert(name.to_string(), id);
+                id
+            }
+        };
+        self.cells[index] = id;
+        Ok(())
+    }
+
+    fn get(&self, x: i32, y: i32, z: i32) -> Option<&str> {
+        let id = self.cells[self.index(x, y, z)?];
+        (id != 0).then(|| self.names[id as usize].as_str())
+    }
+
+    fn is_opaque(&self, x: i32, y: i32, z: i32) -> bool {
+        self.get(x, y, z).is_some_and(|name| !import::is_transparent(name))
+    }
+}
+
+pub fn load_region_box(
+    region_dir: &Path,
+    materials: &MaterialLibrary,
+    scene: &mut Scene,
+    options: &AnvilOptions,
+) -> Result<ImportReport, ImportError> {
+    let min = [
+        options.min[0].min(options.max[0]),
+        options.min[1].min(options.max[1]),
+        options.min[2].min(options.max[2]),
+    ];
+    let max = [
+        options.min[0].max(options.max[0]),
+        options.min[1].max(options.max[1]),
+        options.min[2].max(options.max[2]),
+    ];
+    // En i64 para que una caja que cruza todo el rango de i32 no desborde
+    let extent = [0, 1, 2].map(|i| i64::from(max[i]) - i64::from(min[i]) + 1);
+    let volume = extent[0]
+        .checked_mul(extent[1])
+        .and_then(|v| v.checked_mul(extent[2]))
+        .ok_or_else(|| {
+            ImportError(format!("la caja es demasiado grande; el máximo es {} bloques", MAX_VOLUME))
+        })?;
+    if volume > MAX_VOLUME as i64 {
+        return Err(ImportError(format!(
+            "la caja tiene {} bloques; el máximo es {}",
+            volume, MAX_VOLUME
+        )));
+    }
+    let volume = volume as usize;
+    let size = extent.map(|e| e as usize);
+
+    let mut blocks = BlockBox {
+        min,
+        size,
+        cells: vec![0; volume],
+        // El índice 0 queda reservado para el aire
+        names: vec!["minecraft:air".to_string()],
+        ids: HashMap::new(),
+    };
+
+    // Un archivo de región cubre 32x32 chunks; cada chunk 16x16 columnas
+    let mut regions: HashMap<(i32, i32), Option<Vec<u8>>> = HashMap::new();
+    for cz in (min[2] >> 4)..=(max[2] >> 4) {
+        for cx in (min[0] >> 4)..=(max[0] >> 4) {
+            let (rx, rz) = (cx >> 5, cz >> 5);
+            let region = regions.entry((rx, rz)).or_insert_with(|| {
+                std::fs::read(region_dir.join(format!("r.{}.{}.mca", rx, rz))).ok()
+            });
+            let Some(region) = region else { continue };
+            let Some(chunk) = read_chunk(region_dir, region, cx, cz)? else { continue };
+            load_chunk(&chunk, cx, cz, &mut blocks)
+                .map_err(|e| ImportError(format!("chunk ({}, {}): {}", cx, cz, e)))?;
+        }
+    }
+
+    let mut report = ImportReport::default();
+    for y in min[1]..=max[1] {
+        for z in min[2]..=max[2] {
+            for x in min[0]..=max[0] {
+                let Some(name) = blocks.get(x, y, z) else { continue };
+
+                // Bloque opaco rodeado por 6 bloques opacos: nunca se ve
+                let hidden = !import::is_transparent(name)
+                    && blocks.is_opaque(x + 1, y, z)
+                    && blocks.is_opaque(x - 1, y, z)
+                    && blocks.is_opaque(x, y + 1, z)
+                    && blocks.is_opaque(x, y - 1, z)
+                    && blocks.is_opaque(x, y, z + 1)
+                    && blocks.is_opaque(x, y, z - 1);
+                if hidden {
+                    report.hidden += 1;
+                    continue;
+                }
+
+                let center = import::block_center(options.origin, x - min[0], y - min[1], z - min[2]);
+                import::place_block(scene, materials, &mut report, name, center);
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+// Devuelve el NBT de un chunk, o None si el chunk no se ha generado
+fn read_chunk(region_dir: &Path, region: &[u8], cx: i32, cz: i32) -> Result<Option<Tag>, ImportError> {
+    // Cabecera: 1024 entradas de 4 bytes (offset en sectores de 4 KiB + cantidad de sectores)
+    let entry = (((cx & 31) + (cz & 31) * 32) * 4) as usize;
+    if region.len() < 8192 {
+        return Err(ImportError("archivo de región truncado".to_string()));
+    }
+    let location = &region[entry..entry + 4];
+    let offset = ((location[0] as usize) << 16 | (location[1] as usize) << 8 | location[2] as usize) * 4096;
+    if offset == 0 {
+        return Ok(None);
+    }
+    if offset + 5 > region.len() {
+        return Err(ImportError(format!("chunk ({}, {}) fuera del archivo", cx, cz)));
+    }
+
+    let length = u32::from_be_bytes(region[offset..offset + 4].try_into().unwrap()) as usize;
+    let compression = region[offset + 4];
+    let payload = if compression & 0x80 != 0 {
+        // Chunks muy grandes se guardan aparte en c.X.Z.mcc
+        let path: PathBuf = region_dir.join(format!("c.{}.{}.mcc", cx, cz));
+        std::fs::read(&path)?
+    } else {
+        let end = offset + 4 + length;
+        if length == 0 || end > region.len() {
+            return Err(ImportError(format!("chunk ({}, {}) con longitud inválida", cx, cz)));
+        }
+        region[offset + 5..end].to_vec()
+    };
+
+    match compression & 0x7f {
+        // 1 = gzip, 2 = zlib, 3 = sin comprimir: nbt::read detecta cuál es
+        1..=3 => Ok(Some(nbt::read(&payload)?.1)),
+        other => Err(ImportError(format!("compresión {} no soportada en el chunk ({}, {})", other, cx, cz))),
+    }
+}
+
+fn load_chunk(chunk: &Tag, cx: i32, cz: i32, blocks: &mut BlockBox) -> Result<(), ImportError> {
+    let sections = chunk
+        .get("sections")
+        .and_then(|s| s.as_list())
+        .ok_or_else(|| ImportError("formato anterior a 1.18 (no hay `sections`)".to_string()))?;
+
+    for section in sections {
+        let Some(section_y) = section.get("Y").and_then(|y| y.as_i32()) else { continue };
+        let base_y = section_y * 16;
+        if base_y + 15 < blocks.min[1] || base_y >= blocks.min[1] + blocks.size[1] as i32 {
+            continue;
+        }
+        let Some(states) = section.get("block_states") else { continue };
+        let palette: Vec<&str> = states
+            .get("palette")
+            .and_then(|p| p.as_list())
+            .unwrap_or(&[])
+            .iter()
+            .map(|entry| entry.get("Name").and_then(|n| n.as_str()).unwrap_or("minecraft:air"))
+            .collect();
+        if palette.is_empty() {
+            continue;
+        }
+
+        let data = states.get("data").and_then(|d| d.as_long_array());
+        let indices = unpack_indices(data, palette.len())?;
+
+        for (i, state) in indices.iter().enumerate() {
+            let name = palette
+                .get(*state as usize)
+                .ok_or_else(|| ImportError(format!("índice {} fuera de la paleta", state)))?;
+            let (x, y, z) = (i as i32 & 15, (i as i32 >> 8) & 15, (i as i32 >> 4) & 15);
+            blocks.set(cx * 16 + x, base_y + y, cz * 16 + z, name)?;
+        }
+    }
+    Ok(())
+}
+
+// 4096 índices empaquetados en longs; desde 1.16 los valores no cruzan de un long a otro
+fn unpack_indices(data: Option<&[i64]>, palette_len: usize) -> Result<Vec<u16>, ImportError> {
+    let Some(data) = data else {
+        // Sin `data`: toda la sección es el único bloque de la paleta
+        return Ok(vec![0; 4096]);
+    };
+    let bits = (usize::BITS - (palette_len.max(2) - 1).leading_zeros()).max(4) as usize;
+    let per_long = 64 / bits;
+    let needed = 4096_usize.div_ceil(per_long);
+    if data.len() < needed {
+        return Err(ImportError(format!(
+            "block_states con {} longs, se esperaban {}",
+            data.len(),
+            needed
+        )));
+    }
+    let mask = (1u64 << bits) - 1;
+    let mut indices = Vec::with_capacity(4096);
+    for i in 0..4096 {
+        let long = data[i / per_long] as u64;
+        let shift = (i % per_long) * bits;
+        indices.push(((long >> shift) & mask) as u16);
+    }
+    Ok(indices)
+}
@@ -0,0 +1,352 @@
+// bench.rs
+// Mide cuánto tardan las consultas de intersección sobre la escena cargada,
+// sin abrir la ventana: `cargo run --release -- --bench [escena.json]`.
+use raylib::prelude::*;
+#[cfg(feature = "count-allocs")]
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::HashMap;
+use std::f32::consts::PI;
+#[cfg(feature = "count-allocs")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::camera::Camera;
+use crate::packet::{RayPacket, LANES};
+use crate::framebuffer::Framebuffer;
+use crate::light::Light;
+use crate::material::{Material, MaterialId, TextureFaces, TextureId};
+use crate::materials::MaterialLibrary;
+use crate::ray_intersect::HitInfo;
+use crate::scene::Scene;
+use crate::scene_file::LoadedScene;
+use crate::textures::TextureManager;
+use crate::{render, texture_uv, RenderContext, RenderOptions};
+
+const WIDTH: i32 = 900;
+const HEIGHT: i32 = 700;
+const RUNS: usize = 3;
+
+// Direcciones de los rayos primarios, igual que en render()
+fn primary_rays(camera: &Camera) -> Vec<Vector3> {
+    let width = WIDTH as f32;
+    let height = HEIGHT as f32;
+    let aspect_ratio = width / height;
+    let perspective_scale = (PI / 3.0 * 0.5).tan();
+    let mut rays = Vec::with_capacity((WIDTH * HEIGHT) as usize);
+    for y in 0..HEIGHT {
+        for x in 0..WIDTH {
+            let screen_x = ((2.0 * x as f32) / width - 1.0) * aspect_ratio * perspective_scale;
+            let screen_y = (-(2.0 * y as f32) / height + 1.0) * perspective_scale;
+            let direction = Vector3::new(screen_x, screen_y, -1.0).normalized();
+            rays.push(camera.basis_change(&direction));
+        }
+    }
+    rays
+}
+
+// Mejor tiempo de RUNS corridas y el resultado de la última
+fn time<T>(mut f: impl FnMut() -> T) -> (Duration, T) {
+    let mut best = Duration::MAX;
+    let mut result = None;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        result = Some(f());
+        best = best.min(start.elapsed());
+    }
+    (best, result.unwrap())
+}
+
+// Compara dos formas de resolver las mismas consultas: `names` = (la de antes, la nueva)
+fn report(label: &str, rays: usize, names: (&str, &str), before: Duration, after: Duration) {
+    let mrays = |d: Duration| rays as f64 / d.as_secs_f64() / 1e6;
+    println!(
+        "{:<10} {:>9} rayos   {} {:>9.1} ms ({:>6.2} Mrayos/s)   {} {:>9.1} ms ({:>6.2} Mrayos/s)   x{:.1}",
+        label,
+        rays,
+        names.0,
+        before.as_secs_f64() * 1000.0,
+        mrays(before),
+        names.1,
+        after.as_secs_f64() * 1000.0,
+        mrays(after),
+        before.as_secs_f64() / after.as_secs_f64()
+    );
+}
+
+// Rayos de sombra desde cada punto visible hacia cada luz, parecidos a los de cast_ray.
+// Agrupados por luz, así los de píxeles vecinos quedan juntos como en cast_packet.
+fn shadow_rays(camera: &Camera, lights: &[Light], rays: &[Vector3], hits: &[Option<f32>]) -> Vec<(Vector3, Vector3, f32)> {
+    let mut shadow_rays = Vec::new();
+    for light in lights {
+        for (direction, hit) in rays.iter().zip(hits) {
+            let Some(distance) = hit else { continue };
+            let point = camera.eye + *direction * *distance;
+            let to_light = light.position - point;
+            let length = to_light.length();
+            let dir = to_light.normalized();
+            shadow_rays.push((point + dir * 1e-3, dir, length - 1e-3));
+        }
+    }
+    shadow_rays
+}
+
+fn count_mismatches(a: &[Option<f32>], b: &[Option<f32>]) -> usize {
+    a.iter()
+        .zip(b)
+        .filter(|(a, b)| match (a, b) {
+            (Some(a), Some(b)) => (a - b).abs() > 1e-3,
+            (None, None) => false,
+            _ => true,
+        })
+        .count()
+}
+
+// Compara recorrer todos los objetos contra el BVH en rayos primarios y de sombra (un hilo)
+pub fn run_bvh(scene: &Scene, camera: &Camera, lights: &[Light]) {
+    println!("Escena: {} objetos, {}x{} píxeles, {} luces", scene.objects.len(), WIDTH, HEIGHT, lights.len());
+    let rays = primary_rays(camera);
+
+    let (linear_time, linear_hits) = time(|| {
+        rays.iter()
+            .map(|d| scene.closest_hit_linear(&camera.eye, d).map(|h| h.distance))
+            .collect::<Vec<_>>()
+    });
+    let (bvh_time, bvh_hits) = time(|| {
+        rays.iter()
+            .map(|d| scene.closest_hit(&camera.eye, d).map(|h| h.distance))
+            .collect::<Vec<_>>()
+    });
+    report("primarios", rays.len(), ("lineal", "BVH"), linear_time, bvh_time);
+
+    let mismatches = count_mismatches(&linear_hits, &bvh_hits);
+    if mismatches > 0 {
+        println!("¡Atención! {} rayos primarios dan otro resultado con el BVH", mismatches);
+    }
+
+    let shadow_rays = shadow_rays(camera, lights, &rays, &linear_hits);
+    let (linear_time, linear_blocked) = time(|| {
+        shadow_rays.iter().filter(|(o, d, max)| scene.any_hit_linear(o, d, *max)).count()
+    });
+    let (bvh_time, bvh_blocked) = time(|| shadow_rays.iter().filter(|(o, d, max)| scene.any_hit(o, d, *max)).count());
+    report("sombras", shadow_rays.len(), ("lineal", "BVH"), linear_time, bvh_time);
+    if linear_blocked != bvh_blocked {
+        println!(
+            "¡Atención! rayos de sombra bloqueados: {} lineal, {} con BVH",
+            linear_blocked, bvh_blocked
+        );
+    }
+}
+
+// Compara el BVH rayo por rayo contra paquetes de LANES rayos vecinos (un hilo)
+pub fn run_packets(scene: &Scene, camera: &Camera, lights: &[Light]) {
+    let rays = primary_rays(camera);
+    let packet = |origins: [Vector3; LANES], chunk: &[Vector3]| {
+        let mut directions = [Vector3::new(0.0, 0.0, -1.0); LANES];
+        directions[..chunk.len()].copy_from_slice(chunk);
+        RayPacket::new(origins, directions, std::array::from_fn(|lane| lane < chunk.len()))
+    };
+    let packets: Vec<RayPacket> = rays.chunks(LANES).map(|chunk| packet([camera.eye; LANES], chunk)).collect();
+
+    let (scalar_time, scalar_hits) = time(|| {
+        rays.iter()
+            .map(|d| scene.closest_hit(&camera.eye, d).map(|h| h.distance))
+            .collect::<Vec<_>>()
+    });
+    let (packet_time, packet_hits) = time(|| {
+        packets
+            .iter()
+            .flat_map(|p| scene.closest_hit_packet(p).map(|h| h.map(|h| h.distance)))
+            .collect::<Vec<_>>()
+    });
+    report("primarios", rays.len(), ("escalar", "paquetes"), scalar_time, packet_time);
+    let mismatches = count_mismatches(&scalar_hits, &packet_hits[..rays.len()]);
+    if mismatches > 0 {
+        println!("¡Atención! {} rayos primarios dan otro resultado en paquete", mismatches);
+    }
+
+    let shadow_rays = shadow_rays(camera, lights, &rays, &scalar_hits);
+    let shadow_packets: Vec<(RayPacket, [f32; LANES])> = shadow_rays
+        .chunks(LANES)
+        .map(|chunk| {
+            let origins = std::array::from_fn(|lane| chunk.get(lane).map_or(Vector3::zero(), |r| r.0));
+            let directions: Vec<Vector3> = chunk.iter().map(|r| r.1).collect();
+            let max_dist = std::array::from_fn(|lane| chunk.get(lane).map_or(0.0, |r| r.2));
+            (packet(origins, &directions), max_dist)
+        })
+        .collect();
+    let (scalar_time, scalar_blocked) =
+        time(|| shadow_rays.iter().filter(|(o, d, max)| scene.any_hit(o, d, *max)).count());
+    let (packet_time, packet_blocked) = time(|| {
+        shadow_packets
+            .iter()
+            .map(|(p, max)| scene.any_hit_packet(p, max).iter().filter(|b| **b).count())
+            .sum::<usize>()
+    });
+    report("sombras", shadow_rays.len(), ("escalar", "paquetes"), scalar_time, packet_time);
+    if scalar_blocked != packet_blocked {
+        println!(
+            "¡Atención! rayos de sombra bloqueados: {} escalar, {} en paquete",
+            scalar_blocked, packet_blocked
+        );
+    }
+}
+
+// Asignador que cuenta las reservas de memoria; con la feature count-allocs main.rs lo instala
+// como #[global_allocator] para que el benchmark pueda informar cuántas se hacen
+#[cfg(feature = "count-allocs")]
+pub struct CountingAlloc;
+
+#[cfg(feature = "count-allocs")]
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(feature = "count-allocs")]
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+// Reservas de memoria que hizo `f`; None si no se compiló con la feature count-allocs
+fn count_allocations<T>(f: impl FnOnce() -> T) -> (T, Option<usize>) {
+    #[cfg(feature = "count-allocs")]
+    {
+        let before = ALLOCATIONS.load(Ordering::Relaxed);
+        let result = f();
+        (result, Some(ALLOCATIONS.load(Ordering::Relaxed) - before))
+    }
+    #[cfg(not(feature = "count-allocs"))]
+    (f(), None)
+}
+
+fn allocations_label(allocations: Option<usize>) -> String {
+    allocations.map_or("?".to_string(), |n| n.to_string())
+}
+
+// Texturas solo en CPU, sin ventana
+fn load_textures(materials: &MaterialLibrary) -> Option<TextureManager> {
+    let mut texture_manager = TextureManager::new();
+    match texture_manager.load_cpu(&materials.textures) {
+        Ok(()) => Some(texture_manager),
+        Err(e) => {
+            println!("No se pudieron cargar las texturas: {}", e);
+            None
+        }
+    }
+}
+
+// Material como era antes de los ids: cada impacto lo copiaba entero (con las cuatro rutas
+// de sus texturas) y al muestrear se buscaba la ruta en un HashMap
+#[derive(Clone)]
+struct ClonedMaterial {
+    material: Material,
+    textures: Option<TextureFaces<String>>,
+}
+
+// Compara, sobre los mismos impactos primarios, buscar el material y muestrear su textura como
+// antes (copiando el material y buscando la ruta) contra hacerlo con MaterialId y TextureId
+pub fn run_materials(loaded: &LoadedScene, materials: &MaterialLibrary) {
+    let Some(texture_manager) = load_textures(materials) else { return };
+    let camera = &loaded.camera;
+    let hits: Vec<HitInfo> =
+        primary_rays(camera).iter().filter_map(|d| loaded.scene.closest_hit(&camera.eye, d)).collect();
+
+    let by_path: HashMap<String, TextureId> =
+        materials.textures.iter().map(|(id, path)| (path.to_string(), id)).collect();
+    let count = hits.iter().map(|hit| hit.material.0 as usize + 1).max().unwrap_or(0);
+    let cloned: Vec<ClonedMaterial> = (0..count as u32)
+        .map(|i| {
+            let material = materials[MaterialId(i)];
+            let textures = material.textures.map(|faces| faces.map(|id| materials.textures.path(*id).to_string()));
+            ClonedMaterial { material, textures }
+        })
+        .collect();
+
+    let mut allocations = (None, None);
+    let (cloned_time, cloned_sum) = time(|| {
+        let (sum, used) = count_allocations(|| {
+            hits.iter()
+                .map(|hit| {
+                    let m = cloned[hit.material.0 as usize].clone();
+                    let Some(faces) = &m.textures else { return Vector3::one() };
+                    let path = faces.face(hit.local_normal).clone();
+                    let (_, u, v) = texture_uv(hit, &m.material).expect("material con texturas");
+                    by_path.get(&path).map_or(Vector3::one(), |&id| texture_manager.sample_uv(id, u, v, 0.0))
+                })
+                .fold(Vector3::zero(), |acc, color| acc + color)
+        });
+        allocations.0 = used;
+        sum
+    });
+    let (id_time, id_sum) = time(|| {
+        let (sum, used) = count_allocations(|| {
+            hits.iter()
+                .map(|hit| {
+                    let m = &materials[hit.material];
+                    texture_uv(hit, m).map_or(Vector3::one(), |(face, u, v)| texture_manager.sample_uv(face, u, v, 0.0))
+                })
+                .fold(Vector3::zero(), |acc, color| acc + color)
+        });
+        allocations.1 = used;
+        sum
+    });
+    report("materiales", hits.len(), ("clonando", "ids"), cloned_time, id_time);
+    match allocations {
+        (Some(cloned), Some(ids)) => println!("{:<10} reservas de memoria: {} clonando, {} con ids", "", cloned, ids),
+        _ => println!("{:<10} (las reservas de memoria se cuentan compilando con --features count-allocs)", ""),
+    }
+    if cloned_sum != id_sum {
+        println!("¡Atención! las texturas muestreadas no coinciden");
+    }
+}
+
+// Renderiza cuadros completos en un solo hilo (texturas solo en CPU) y mide
+// el tiempo y las reservas de memoria por cuadro
+pub fn run_render(loaded: &LoadedScene, materials: &MaterialLibrary, options: &RenderOptions) {
+    let Some(texture_manager) = load_textures(materials) else { return };
+    let ctx = RenderContext {
+        scene: &loaded.scene,
+        lights: &loaded.lights,
+        materials,
+        texture_manager: &texture_manager,
+        skybox: &loaded.skybox,
+        time: 0.0,
+    };
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(1).build().expect("pool de un hilo");
+    let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT, Color::BLACK);
+
+    // Un cuadro con cast_ray píxel por píxel y otro con paquetes, para comparar
+    let mut frames = Vec::new();
+    for packets in [false, true] {
+        let options = RenderOptions { packets, ..*options };
+        let mut allocations = None;
+        let (frame_time, _) = time(|| {
+            // Cada corrida traza la primera muestra de nuevo, sin acumular sobre la anterior
+            framebuffer.reset_accumulation();
+            let (_, used) = count_allocations(|| pool.install(|| render(&mut framebuffer, &loaded.camera, &ctx, &options, 1)));
+            allocations = allocations.into_iter().chain(used).min();
+        });
+        println!(
+            "{:<10} {:>9} píxeles {:>9.1} ms por cuadro (1 hilo)   {} reservas de memoria por cuadro",
+            if packets { "paquetes" } else { "render" },
+            WIDTH * HEIGHT,
+            frame_time.as_secs_f64() * 1000.0,
+            allocations_label(allocations)
+        );
+        let pixels = (0..HEIGHT).flat_map(|y| (0..WIDTH).map(move |x| (x, y)));
+        frames.push(pixels.map(|(x, y)| framebuffer.get_pixel_color(x, y)).collect::<Vec<_>>());
+    }
+    let different = frames[0].iter().zip(&frames[1]).filter(|(a, b)| a != b).count();
+    if different > 0 {
+        println!("{} píxeles cambian con paquetes (empates entre caras a la misma distancia)", different);
+    }
+}
@@ -0,0 +1,506 @@
+// bvh.rs
+// Jerarquía de cajas (BVH) sobre los objetos de la escena para no probar
+// cada rayo contra todos los cubos. Se construye con SAH por bins y se
+// guarda en arreglos planos (nodos e índices) en lugar de punteros.
+use raylib::prelude::*;
+
+use crate::packet::{RayPacket, LANES};
+use crate::ray_intersect::{HitInfo, RayIntersect};
+
+// Caja alineada a los ejes en espacio mundo
+#[derive(Clone, Copy)]
+pub struct Aabb {
+    pub min: Vector3,
+    pub max: Vector3,
+}
+
+impl Aabb {
+    pub fn empty() -> Self {
+        Aabb {
+            min: Vector3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+            max: Vector3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+        }
+    }
+
+    pub fn grow(&mut self, p: Vector3) {
+        self.min = Vector3::new(self.min.x.min(p.x), self.min.y.min(p.y), self.min.z.min(p.z));
+        self.max = Vector3::new(self.max.x.max(p.x), self.max.y.max(p.y), self.max.z.max(p.z));
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        let mut result = *self;
+        result.grow(other.min);
+        result.grow(other.max);
+        result
+    }
+
+    pub fn centroid(&self) -> Vector3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        let d = self.max - self.min;
+        if d.x < 0.0 || d.y < 0.0 || d.z < 0.0 {
+            return 0.0;
+        }
+        2.0 * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+
+    // Distancia a la que el rayo entra a la caja (0 si empieza adentro), si la toca antes de max_t.
+    // inv_dir = 1 / dirección; min/max de f32 ignoran los NaN de 0 * inf.
+    pub fn hit(&self, origin: &Vector3, inv_dir: &Vector3, max_t: f32) -> Option<f32> {
+        let tx1 = (self.min.x - origin.x) * inv_dir.x;
+        let tx2 = (self.max.x - origin.x) * inv_dir.x;
+        let mut t_near = tx1.min(tx2);
+        let mut t_far = tx1.max(tx2);
+
+        let ty1 = (self.min.y - origin.y) * inv_dir.y;
+        let ty2 = (self.max.y - origin.y) * inv_dir.y;
+        t_near = t_near.max(ty1.min(ty2));
+        t_far = t_far.min(ty1.max(ty2));
+
+        let tz1 = (self.min.z - origin.z) * inv_dir.z;
+        let tz2 = (self.max.z - origin.z) * inv_dir.z;
+        t_near = t_near.max(tz1.min(tz2));
+        t_far = t_far.min(tz1.max(tz2));
+
+        if t_far >= t_near && t_far >= 0.0 && t_near <= max_t {
+            Some(t_near.max(0.0))
+        } else {
+            None
+        }
+    }
+
+    // ¿Algún carril del paquete toca la caja?
+    pub fn hit_any(&self, packet: &RayPacket, max_t: &[f32; LANES]) -> bool {
+        packet.hit_aabb(self, max_t).0.contains(&true)
+    }
+}
+
+// Hoja: objetos indices[first..first + count]. Interior (count == 0): hijos en first y first + 1.
+#[derive(Clone, Copy)]
+struct Node {
+    bounds: Aabb,
+    first: u32,
+    count: u32,
+}
+
+pub struct Bvh {
+    nodes: Vec<Node>,
+    indices: Vec<u32>,
+}
+
+const BINS: usize = 12;
+const MAX_LEAF: usize = 4;
+// Costo de recorrer un nodo relativo al de intersectar un objeto
+const TRAVERSAL_COST: f32 = 1.0;
+const STACK_SIZE: usize = 64;
+// Un recorrido en profundidad nunca tiene más de profundidad + 1 nodos apilados: más abajo de
+// esto los nodos quedan como hojas aunque tengan muchos objetos, para no pasarse de la pila
+const MAX_DEPTH: usize = STACK_SIZE - 1;
+
+impl Bvh {
+    // `bounds[i]` es la caja del objeto i
+    pub fn build(bounds: &[Aabb]) -> Self {
+        let mut bvh = Bvh {
+            nodes: Vec::with_capacity(bounds.len() * 2),
+            indices: (0..bounds.len() as u32).collect(),
+        };
+        if bounds.is_empty() {
+            return bvh;
+        }
+        let centroids: Vec<Vector3> = bounds.iter().map(|b| b.centroid()).collect();
+        bvh.nodes.push(Node { bounds: Aabb::empty(), first: 0, count: bounds.len() as u32 });
+        bvh.subdivide(0, bounds, &centroids, 0);
+        bvh
+    }
+
+    fn subdivide(&mut self, node_index: usize, bounds: &[Aabb], centroids: &[Vector3], depth: usize) {
+        let first = self.nodes[node_index].first as usize;
+        let count = self.nodes[node_index].count as usize;
+        let items = &mut self.indices[first..first + count];
+
+        let mut node_bounds = Aabb::empty();
+        let mut centroid_bounds = Aabb::empty();
+        for &i in items.iter() {
+            node_bounds = node_bounds.union(&bounds[i as usize]);
+            centroid_bounds.grow(centroids[i as usize]);
+        }
+        self.nodes[node_index].bounds = node_bounds;
+        if count <= 1 || depth >= MAX_DEPTH {
+            return;
+        }
+
+        // Mejor corte según SAH, probando BINS - 1 planos por eje
+        let mut best: Option<(usize, usize, f32)> = None;
+        for axis in 0..3 {
+            let (lo, hi) = (component(centroid_bounds.min, axis), component(centroid_bounds.max, axis));
+            if hi - lo <= 1e-6 {
+                continue;
+            }
+            let scale = BINS as f32 / (hi - lo);
+            let bin_of = |c: f32| (((c - lo) * scale) as usize).min(BINS - 1);
+
+            let mut bin_bounds = [Aabb::empty(); BINS];
+            let mut bin_count = [0usize; BINS];
+            for &i in items.iter() {
+                let b = bin_of(component(centroids[i as usize], axis));
+                bin_bounds[b] = bin_bounds[b].union(&bounds[i as usize]);
+                bin_count[b] += 1;
+            }
+
+            // Barrido de izquierda a derecha y de derecha a izquierda
+            let mut left_area = [0.0; BINS - 1];
+            let mut left_count = [0usize; BINS - 1];
+            let mut acc = Aabb::empty();
+            let mut n = 0;
+            for b in 0..BINS - 1 {
+                acc = acc.union(&bin_bounds[b]);
+                n += bin_count[b];
+                left_area[b] = acc.surface_area();
+                left_count[b] = n;
+            }
+            let mut acc = Aabb::empty();
+            let mut n = 0;
+            for b in (1..BINS).rev() {
+                acc = acc.union(&bin_bounds[b]);
+                n += bin_count[b];
+                let split = b - 1;
+                if left_count[split] == 0 || n == 0 {
+                    continue;
+                }
+                let cost = left_count[split] as f32 * left_area[split] + n as f32 * acc.surface_area();
+                if best.is_none_or(|(_, _, c)| cost < c) {
+                    best = Some((axis, split, cost));
+                }
+            }
+        }
+
+        let parent_area = node_bounds.surface_area().max(1e-12);
+        let leaf_cost = count as f32;
+        let Some((axis, split, cost)) = best else { return };
+        let split_cost = TRAVERSAL_COST + cost / parent_area;
+        if count <= MAX_LEAF && split_cost >= leaf_cost {
+            return;
+        }
+
+        // Partición en el lugar: los del lado izquierdo del plano quedan primero
+        let lo = component(centroid_bounds.min, axis);
+        let scale = BINS as f32 / (component(centroid_bounds.max, axis) - lo);
+        let mut left = 0;
+        for j in 0..items.len() {
+            let c = component(centroids[items[j] as usize], axis);
+            if ((((c - lo) * scale) as usize).min(BINS - 1)) <= split {
+                items.swap(left, j);
+                left += 1;
+            }
+        }
+
+        let child = self.nodes.len();
+        self.nodes.push(Node { bounds: Aabb::empty(), first: first as u32, count: left as u32 });
+        self.nodes.push(Node { bounds: Aabb::empty(), first: (first + left) as u32, count: (count - left) as u32 });
+        self.nodes[node_index].first = child as u32;
+        self.nodes[node_index].count = 0;
+        self.subdivide(child, bounds, centroids, depth + 1);
+        self.subdivide(child + 1, bounds, centroids, depth + 1);
+    }
+
+    // Recalcula las cajas de abajo hacia arriba sin cambiar el árbol, para cuando los
+    // objetos se movieron. `bounds[i]` es la caja nueva del objeto i. Si se movieron mucho el
+    // árbol queda peor que uno armado de cero, pero sigue dando los mismos impactos
+    pub fn refit(&mut self, bounds: &[Aabb]) {
+        // Los hijos siempre quedan después del padre: de atrás hacia adelante ya están listos
+        for i in (0..self.nodes.len()).rev() {
+            let node = self.nodes[i];
+            self.nodes[i].bounds = if node.count > 0 {
+                self.indices[node.first as usize..(node.first + node.count) as usize]
+                    .iter()
+                    .fold(Aabb::empty(), |acc, &j| acc.union(&bounds[j as usize]))
+            } else {
+                let (a, b) = (node.first as usize, node.first as usize + 1);
+                self.nodes[a].bounds.union(&self.nodes[b].bounds)
+            };
+        }
+    }
+
+    // Impacto más cercano entre todos los objetos
+    pub fn closest_hit<T: RayIntersect>(
+        &self,
+        objects: &[T],
+        origin: &Vector3,
+        direction: &Vector3,
+    ) -> Option<HitInfo> {
+        self.closest_hit_with(origin, direction, |i| objects[i as usize].ray_intersect(origin, direction))
+    }
+
+    // closest_hit en el que `hit_object(i)` prueba el objeto i de las hojas; así las hojas
+    // pueden ser otros BVH (el TLAS de la escena)
+    pub fn closest_hit_with(
+        &self,
+        origin: &Vector3,
+        direction: &Vector3,
+        mut hit_object: impl FnMut(u32) -> Option<HitInfo>,
+    ) -> Option<HitInfo> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+        let inv_dir = Vector3::new(1.0 / direction.x, 1.0 / direction.y, 1.0 / direction.z);
+        let mut closest: Option<HitInfo> = None;
+        let mut best = f32::INFINITY;
+
+        let mut stack = [0u32; STACK_SIZE];
+        let mut top = 0;
+        if self.nodes[0].bounds.hit(origin, &inv_dir, best).is_some() {
+            stack[0] = 0;
+            top = 1;
+        }
+        while top > 0 {
+            top -= 1;
+            let node = &self.nodes[stack[top] as usize];
+            if node.count > 0 {
+                for &i in &self.indices[node.first as usize..(node.first + node.count) as usize] {
+                    if let Some(hit) = hit_object(i) {
+                        if hit.distance < best {
+                            best = hit.distance;
+                            closest = Some(hit);
+                        }
+                    }
+                }
+                continue;
+            }
+
+            // Se apila primero el hijo lejano para visitar antes el cercano
+            let (a, b) = (node.first, node.first + 1);
+            let ta = self.nodes[a as usize].bounds.hit(origin, &inv_dir, best);
+            let tb = self.nodes[b as usize].bounds.hit(origin, &inv_dir, best);
+            let (near, far) = match (ta, tb) {
+                (Some(ta), Some(tb)) if tb < ta => ((b, Some(tb)), (a, Some(ta))),
+                _ => ((a, ta), (b, tb)),
+            };
+            for (child, t) in [far, near] {
+                if t.is_some() {
+                    stack[top] = child;
+                    top += 1;
+                }
+            }
+        }
+        closest
+    }
+
+    // ¿Hay algún objeto entre origin y origin + direction * max_dist? (para sombras)
+    pub fn any_hit<T: RayIntersect>(
+        &self,
+        objects: &[T],
+        origin: &Vector3,
+        direction: &Vector3,
+        max_dist: f32,
+    ) -> bool {
+        self.first_hit(objects, origin, direction, max_dist).is_some()
+    }
+
+    // Como any_hit pero devuelve el primer impacto que encontró antes de max_dist (no
+    // necesariamente el más cercano)
+    pub fn first_hit<T: RayIntersect>(
+        &self,
+        objects: &[T],
+        origin: &Vector3,
+        direction: &Vector3,
+        max_dist: f32,
+    ) -> Option<HitInfo> {
+        self.first_hit_with(origin, direction, max_dist, |i| objects[i as usize].ray_intersect(origin, direction))
+    }
+
+    // first_hit con las hojas probadas por `hit_object(i)`, como closest_hit_with
+    pub fn first_hit_with(
+        &self,
+        origin: &Vector3,
+        direction: &Vector3,
+        max_dist: f32,
+        mut hit_object: impl FnMut(u32) -> Option<HitInfo>,
+    ) -> Option<HitInfo> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+        let inv_dir = Vector3::new(1.0 / direction.x, 1.0 / direction.y, 1.0 / direction.z);
+        let mut stack = [0u32; STACK_SIZE];
+        let mut top = 1;
+        while top > 0 {
+            top -= 1;
+            let node = &self.nodes[stack[top] as usize];
+            if node.bounds.hit(origin, &inv_dir, max_dist).is_none() {
+                continue;
+            }
+            if node.count > 0 {
+                for &i in &self.indices[node.first as usize..(node.first + node.count) as usize] {
+                    if let Some(hit) = hit_object(i) {
+                        if hit.distance < max_dist {
+                            return Some(hit);
+                        }
+                    }
+                }
+            } else {
+                stack[top] = node.first;
+                stack[top + 1] = node.first + 1;
+                top += 2;
+            }
+        }
+        None
+    }
+
+    // closest_hit para un paquete de rayos: cada nodo se prueba una vez para todos los
+    // carriles y se baja mientras alguno lo toque. Los objetos de las hojas se prueban
+    // rayo por rayo, solo con los carriles que llegaron a la hoja.
+    pub fn closest_hit_packet<T: RayIntersect>(
+        &self,
+        objects: &[T],
+        packet: &RayPacket,
+    ) -> [Option<HitInfo>; LANES] {
+        self.closest_hit_packet_with(packet, |i, mask, closest| {
+            for lane in (0..LANES).filter(|&lane| mask[lane]) {
+                let hit = objects[i as usize].ray_intersect(&packet.origins[lane], &packet.directions[lane]);
+                closest[lane] = nearest(closest[lane].take(), hit);
+            }
+        })
+    }
+
+    // closest_hit_packet en el que `hit_object(i, mask, closest)` prueba el objeto i con los
+    // carriles de `mask` y deja en `closest` el impacto más cercano de cada uno
+    pub fn closest_hit_packet_with(
+        &self,
+        packet: &RayPacket,
+        mut hit_object: impl FnMut(u32, [bool; LANES], &mut [Option<HitInfo>; LANES]),
+    ) -> [Option<HitInfo>; LANES] {
+        let mut closest: [Option<HitInfo>; LANES] = Default::default();
+        if self.nodes.is_empty() {
+            return closest;
+        }
+        let mut best = [f32::INFINITY; LANES];
+
+        let mut stack = [0u32; STACK_SIZE];
+        let mut top = 0;
+        if self.nodes[0].bounds.hit_any(packet, &best) {
+            stack[0] = 0;
+            top = 1;
+        }
+        while top > 0 {
+            top -= 1;
+            let node = &self.nodes[stack[top] as usize];
+            if node.count > 0 {
+                // `best` pudo bajar desde que se apiló la hoja
+                let (mask, _) = packet.hit_aabb(&node.bounds, &best);
+                for &i in &self.indices[node.first as usize..(node.first + node.count) as usize] {
+                    hit_object(i, mask, &mut closest);
+                }
+                best = closest.each_ref().map(|hit| hit.as_ref().map_or(f32::INFINITY, |hit| hit.distance));
+                continue;
+            }
+
+            // Primero el hijo al que algún carril entra más cerca, como en closest_hit
+            let (a, b) = (node.first, node.first + 1);
+            let (mask_a, ta) = packet.hit_aabb(&self.nodes[a as usize].bounds, &best);
+            let (mask_b, tb) = packet.hit_aabb(&self.nodes[b as usize].bounds, &best);
+            let (hit_a, hit_b) = (mask_a.contains(&true), mask_b.contains(&true));
+            let (near, far) = if hit_a && hit_b && tb < ta {
+                ((b, hit_b), (a, hit_a))
+            } else {
+                ((a, hit_a), (b, hit_b))
+            };
+            for (child, hit) in [far, near] {
+                if hit {
+                    stack[top] = child;
+                    top += 1;
+                }
+            }
+        }
+        closest
+    }
+
+    // any_hit para un paquete: un carril se apaga apenas encuentra algo antes de max_dist[i]
+    pub fn any_hit_packet<T: RayIntersect>(
+        &self,
+        objects: &[T],
+        packet: &RayPacket,
+        max_dist: &[f32; LANES],
+    ) -> [bool; LANES] {
+        self.first_hit_packet(objects, packet, max_dist).each_ref().map(Option::is_some)
+    }
+
+    // first_hit para un paquete de rayos
+    pub fn first_hit_packet<T: RayIntersect>(
+        &self,
+        objects: &[T],
+        packet: &RayPacket,
+        max_dist: &[f32; LANES],
+    ) -> [Option<HitInfo>; LANES] {
+        self.first_hit_packet_with(packet, max_dist, |i, mask, blocked| {
+            for lane in (0..LANES).filter(|&lane| mask[lane]) {
+                let hit = objects[i as usize].ray_intersect(&packet.origins[lane], &packet.directions[lane]);
+                blocked[lane] = hit.filter(|hit| hit.distance < max_dist[lane]);
+            }
+        })
+    }
+
+    // first_hit_packet en el que `hit_object(i, mask, blocked)` prueba el objeto i con los
+    // carriles de `mask` (los que siguen libres) y anota en `blocked` lo que tape cada uno
+    // antes de max_dist
+    pub fn first_hit_packet_with(
+        &self,
+        packet: &RayPacket,
+        max_dist: &[f32; LANES],
+        mut hit_object: impl FnMut(u32, [bool; LANES], &mut [Option<HitInfo>; LANES]),
+    ) -> [Option<HitInfo>; LANES] {
+        let mut blocked: [Option<HitInfo>; LANES] = Default::default();
+        if self.nodes.is_empty() {
+            return blocked;
+        }
+        // Los carriles ya bloqueados no siguen bajando: su distancia máxima pasa a -inf
+        let mut limit = *max_dist;
+        let mut stack = [0u32; STACK_SIZE];
+        let mut top = 1;
+        while top > 0 {
+            top -= 1;
+            let node = &self.nodes[stack[top] as usize];
+            let (mask, _) = packet.hit_aabb(&node.bounds, &limit);
+            if !mask.contains(&true) {
+                continue;
+            }
+            if node.count > 0 {
+                for &i in &self.indices[node.first as usize..(node.first + node.count) as usize] {
+                    let free = std::array::from_fn(|lane| mask[lane] && blocked[lane].is_none());
+                    if free.contains(&true) {
+                        hit_object(i, free, &mut blocked);
+                    }
+                }
+                for lane in 0..LANES {
+                    if blocked[lane].is_some() {
+                        limit[lane] = f32::NEG_INFINITY;
+                    }
+                }
+                if (0..LANES).all(|lane| blocked[lane].is_some() || !packet.active[lane]) {
+                    break;
+                }
+            } else {
+                stack[top] = node.first;
+                stack[top + 1] = node.first + 1;
+                top += 2;
+            }
+        }
+        blocked
+    }
+}
+
+// El más cercano de dos impactos (con empate, el primero)
+pub fn nearest(a: Option<HitInfo>, b: Option<HitInfo>) -> Option<HitInfo> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(if b.distance < a.distance { b } else { a }),
+        (a, b) => a.or(b),
+    }
+}
+
+fn component(v: Vector3, axis: usize) -> f32 {
+    match axis {
+        0 => v.x,
+        1 => v.y,
+        _ => v.z,
+    }
+}
@@ -8,6 +8,9 @@ pub struct Camera {
 
     pub forward: Vector3, //adelante
     pub right: Vector3, //a la derecha (calculado)
+
+    // Sube cada vez que cambia la cámara, para saber cuándo descartar las muestras acumuladas
+    pub revision: u64,
 }
 
 impl Camera {
@@ -19,6 +22,7 @@ impl Camera {
             up,
             forward: Vector3::zero(),
             right: Vector3::zero(),
+            revision: 0,
         };
 
         camera.update_basis();
@@ -30,6 +34,7 @@ impl Camera {
         self.forward = (self.center - self.eye).normalized();
         self.right = self.forward.cross(self.up).normalized();
         self.up = self.right.cross(self.forward);
+        self.revision += 1;
     }
 
     pub fn zoom(&mut self, delta: f32) {
@@ -1,6 +1,7 @@
 use raylib::prelude::*;
 use crate::ray_intersect::{RayIntersect, HitInfo};
-use crate::material::Material;
+use crate::material::MaterialId;
+use crate::bvh::Aabb;
 use std::f32;
 
 pub struct Cube {
@@ -10,7 +11,10 @@ pub struct Cube {
     // Rotación en radianes (rotar primero X, luego Y) — puedes ajustar rx, ry
     pub rot_x: f32,
     pub rot_y: f32,
-    pub material: Material, //la propiedades, color, reflectividad, albedo etc.
+    pub material: MaterialId, //la propiedades, color, reflectividad, albedo etc.
+    // Se suma al punto local de las UVs: una caja que junta varios bloques repite la
+    // textura con la misma fase que tenían los bloques sueltos (cero en un cubo normal)
+    pub uv_offset: Vector3,
 }
 
 impl Cube {
@@ -25,7 +29,7 @@ impl Cube {
     }
 
     // rota vector por Y
-    fn rotate_y(v: Vector3, angle: f32) -> Vector3 {
+    pub fn rotate_y(v: Vector3, angle: f32) -> Vector3 {
         let (s, c) = angle.sin_cos();
         Vector3::new(
             v.x * c + v.z * s,
@@ -36,7 +40,7 @@ impl Cube {
 
     //rotacions combinadas
     // Aplicar rotación forward: Rx then Ry -> v' = Ry(Rx(v))
-    fn rotate_forward(&self, v: Vector3) -> Vector3 {
+    pub fn rotate_forward(&self, v: Vector3) -> Vector3 {
         let v = Cube::rotate_x(v, self.rot_x);
         Cube::rotate_y(v, self.rot_y)
     }
@@ -124,18 +128,6 @@ impl RayIntersect for Cube {
             }
         }
 
-        // Elegir textura según la cara golpeada
-        let face_texture = if local_normal.y > 0.9 {
-            self.material.texture_path.as_ref().map(|t| &t.top)
-        } else if local_normal.y < -0.9 {
-            self.material.texture_path.as_ref().map(|t| &t.bottom)
-        } else if local_normal.x.abs() > 0.9 {
-            self.material.texture_path.as_ref().map(|t| &t.side_x)
-        } else {
-            self.material.texture_path.as_ref().map(|t| &t.side_z)
-        };
-
-
         // Transformar punto y normal de vuelta a espacio mundo
         let world_point = self.rotate_forward(local_hit) + self.center;
         // Normales rotan con la rotación forward (rotación sin translación)
@@ -145,16 +137,33 @@ impl RayIntersect for Cube {
        Some(HitInfo {
                 hit: true,
                 point: world_point,
-                local_point: local_hit,            // punto en espacio local del cubo
+                local_point: local_hit + self.uv_offset, // punto en espacio local del cubo (para UVs)
                 normal: world_normal,
                 local_normal,                      // normal en espacio local
                 distance: t,
-                texture_path: face_texture.cloned(),
-                material: self.material.clone(),
+                material: self.material,
                 
             })
     }
 
+    // Caja de las 8 esquinas ya rotadas
+    fn aabb(&self) -> Aabb {
+        let mut bounds = Aabb::empty();
+        for sx in [-1.0, 1.0] {
+            for sy in [-1.0, 1.0] {
+                for sz in [-1.0, 1.0] {
+                    let corner = Vector3::new(sx * self.half_size.x, sy * self.half_size.y, sz * self.half_size.z);
+                    bounds.grow(self.rotate_forward(corner) + self.center);
+                }
+            }
+        }
+        bounds
+    }
+
+    fn as_cube(&self) -> Option<&Cube> {
+        Some(self)
+    }
+
     
 }
 
@@ -0,0 +1,471 @@
+// export.rs
+// Exporta la geometría de la escena a Wavefront OBJ+MTL y a glTF 2.0 binario (.glb)
+// para abrirla en Blender. Las UVs siguen el mismo mosaico que usa cast_ray
+// (uv_repeat con half_size 1: una textura cada 2 unidades en espacio local).
+use raylib::prelude::*;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use crate::cube::Cube;
+use crate::light::Light;
+use crate::material::{Material, MaterialId, TextureId};
+use crate::materials::MaterialLibrary;
+use crate::scene::Scene;
+
+#[derive(Debug)]
+pub struct ExportError(pub String);
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ExportError {}
+
+impl From<std::io::Error> for ExportError {
+    fn from(e: std::io::Error) -> Self {
+        ExportError(e.to_string())
+    }
+}
+
+// Caras que comparten material y textura, ya en espacio mundo
+struct Group {
+    name: String,
+    material: Material,
+    texture: Option<String>,
+    positions: Vec<Vector3>,
+    normals: Vec<Vector3>,
+    // v = 0 abajo, como en TextureManager::sample_uv
+    uvs: Vec<(f32, f32)>,
+    indices: Vec<u32>,
+}
+
+// (normal local, eje de u, eje de v) de cada cara, igual que en cast_ray
+const FACES: [(Vector3, usize, usize); 6] = [
+    (Vector3::new(1.0, 0.0, 0.0), 2, 1),
+    (Vector3::new(-1.0, 0.0, 0.0), 2, 1),
+    (Vector3::new(0.0, 1.0, 0.0), 0, 2),
+    (Vector3::new(0.0, -1.0, 0.0), 0, 2),
+    (Vector3::new(0.0, 0.0, 1.0), 0, 1),
+    (Vector3::new(0.0, 0.0, -1.0), 0, 1),
+];
+
+fn axis(v: Vector3, i: usize) -> f32 {
+    match i {
+        0 => v.x,
+        1 => v.y,
+        _ => v.z,
+    }
+}
+
+fn with_axis(mut v: Vector3, i: usize, value: f32) -> Vector3 {
+    match i {
+        0 => v.x = value,
+        1 => v.y = value,
+        _ => v.z = value,
+    }
+    v
+}
+
+// Nombre legible para el material: el nombre del archivo de textura o "color"
+fn group_name(texture: Option<&String>, index: usize) -> String {
+    let stem = texture
+        .and_then(|t| Path::new(t).file_stem())
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "color".to_string());
+    format!("{}_{}", stem, index)
+}
+
+// Un grupo por material y textura de cara
+fn collect_groups(scene: &Scene, library: &MaterialLibrary) -> Vec<Group> {
+    let mut groups: Vec<Group> = Vec::new();
+    let mut by_key: HashMap<(MaterialId, Option<TextureId>), usize> = HashMap::new();
+
+    let mut add_cube = |cube: &Cube| {
+        let material = &library[cube.material];
+        for (normal, u_axis, v_axis) in FACES {
+            // Misma regla que cast_ray para elegir la textura de la cara
+            let texture = material.textures.as_ref().map(|faces| *faces.face(normal));
+            let index = *by_key.entry((cube.material, texture)).or_insert_with(|| {
+                let texture = texture.map(|id| library.textures.path(id).to_string());
+                groups.push(Group {
+                    name: group_name(texture.as_ref(), groups.len()),
+                    material: *material,
+                    texture,
+                    positions: Vec::new(),
+                    normals: Vec::new(),
+                    uvs: Vec::new(),
+                    indices: Vec::new(),
+                });
+                groups.len() - 1
+            });
+            add_face(&mut groups[index], cube, normal, u_axis, v_axis);
+        }
+    };
+    for object in &scene.objects {
+        object.for_each_cube(&mut add_cube);
+    }
+    // Los cubos animados, en la pose que tienen al exportar
+    for cube in scene.dynamic_cubes() {
+        add_cube(cube);
+    }
+    groups
+}
+
+fn add_face(group: &mut Group, cube: &Cube, normal: Vector3, u_axis: usize, v_axis: usize) {
+    let h = cube.half_size;
+    let n_axis = 3 - u_axis - v_axis;
+    let face_center = with_axis(Vector3::zero(), n_axis, axis(normal, n_axis) * axis(h, n_axis));
+
+    let mut corners: Vec<Vector3> = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+        .iter()
+        .map(|(su, sv)| {
+            let p = with_axis(face_center, u_axis, su * axis(h, u_axis));
+            with_axis(p, v_axis, sv * axis(h, v_axis))
+        })
+        .collect();
+    // Orden antihorario visto desde afuera
+    if (corners[1] - corners[0]).cross(corners[2] - corners[0]).dot(normal) < 0.0 {
+        corners.reverse();
+    }
+
+    let base = group.positions.len() as u32;
+    let world_normal = cube.rotate_forward(normal).normalized();
+    for corner in corners {
+        group.positions.push(cube.rotate_forward(corner) + cube.center);
+        group.normals.push(world_normal);
+        // uv_repeat(coord, 1.0) sin el módulo: el visor repite la textura
+        let uv = corner + cube.uv_offset;
+        group.uvs.push(((axis(uv, u_axis) + 1.0) * 0.5, (axis(uv, v_axis) + 1.0) * 0.5));
+    }
+    group.indices.extend([base, base + 1, base + 2, base, base + 2, base + 3]);
+}
+
+// Copia las texturas a `<carpeta>/textures/` y devuelve ruta original -> ruta relativa
+fn copy_textures(groups: &[Group], out_dir: &Path) -> Result<HashMap<String, String>, ExportError> {
+    let mut copied: HashMap<String, String> = HashMap::new();
+    let mut used_names: Vec<String> = Vec::new();
+    for texture in groups.iter().filter_map(|g| g.texture.as_ref()) {
+        if copied.contains_key(texture) {
+            continue;
+        }
+        let file_name = Path::new(texture)
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "texture.png".to_string());
+        // Dos packs pueden tener texturas con el mismo nombre
+        let mut name = file_name.clone();
+        let mut n = 1;
+        while used_names.contains(&name) {
+            name = format!("{}_{}", n, file_name);
+            n += 1;
+        }
+        std::fs::create_dir_all(out_dir.join("textures"))?;
+        std::fs::copy(texture, out_dir.join("textures").join(&name))
+            .map_err(|e| ExportError(format!("{}: {}", texture, e)))?;
+        used_names.push(name.clone());
+        copied.insert(texture.clone(), format!("textures/{}", name));
+    }
+    Ok(copied)
+}
+
+pub fn export_obj(scene: &Scene, library: &MaterialLibrary, path: &Path) -> Result<(), ExportError> {
+    let groups = collect_groups(scene, library);
+    let out_dir = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+    let textures = copy_textures(&groups, &out_dir)?;
+    let mtl_path: PathBuf = path.with_extension("mtl");
+    let mtl_name = mtl_path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+
+    let mut obj = String::new();
+    let mut mtl = String::new();
+    writeln!(obj, "# Diorama exportado\nmtllib {}", mtl_name).unwrap();
+
+    // Los índices de OBJ son globales y empiezan en 1
+    let mut offset = 1;
+    for group in &groups {
+        let m = &group.material;
+        writeln!(mtl, "newmtl {}", group.name).unwrap();
+        if group.texture.is_some() {
+            writeln!(mtl, "Kd 1 1 1").unwrap();
+        } else {
+            writeln!(
+                mtl,
+                "Kd {} {} {}",
+                m.diffuse.r as f32 / 255.0,
+                m.diffuse.g as f32 / 255.0,
+                m.diffuse.b as f32 / 255.0
+            )
+            .unwrap();
+        }
+        writeln!(mtl, "Ks {0} {0} {0}", m.albedo[1]).unwrap();
+        writeln!(mtl, "Ns {}", m.specular).unwrap();
+        // Extensión PBR de los .mtl (la leen Blender y la mayoría de los importadores)
+        if let Some(pbr) = &m.pbr {
+            writeln!(mtl, "Pr {}\nPm {}", pbr.roughness, pbr.metallic).unwrap();
+        }
+        let emission = m.emission * m.emission_strength;
+        writeln!(mtl, "Ke {} {} {}", emission.x, emission.y, emission.z).unwrap();
+        writeln!(mtl, "Ni {}", m.refractive_index).unwrap();
+        writeln!(mtl, "d {}", 1.0 - m.transparency).unwrap();
+        // 2 = Blinn-Phong, 3 = con reflejo, 7 = con refracción
+        let illum = if m.transparency > 0.0 { 7 } else if m.reflectivity > 0.0 { 3 } else { 2 };
+        writeln!(mtl, "illum {}", illum).unwrap();
+        if let Some(texture) = group.texture.as_ref().and_then(|t| textures.get(t)) {
+            writeln!(mtl, "map_Kd {}", texture).unwrap();
+            // El alfa de la misma textura recorta la cara
+            if m.alpha_test {
+                writeln!(mtl, "map_d {}", texture).unwrap();
+            }
+        }
+        writeln!(mtl).unwrap();
+
+        writeln!(obj, "o {}\nusemtl {}", group.name, group.name).unwrap();
+        for p in &group.positions {
+            writeln!(obj, "v {} {} {}", p.x, p.y, p.z).unwrap();
+        }
+        for (u, v) in &group.uvs {
+            writeln!(obj, "vt {} {}", u, v).unwrap();
+        }
+        for n in &group.normals {
+            writeln!(obj, "vn {} {} {}", n.x, n.y, n.z).unwrap();
+        }
+        for tri in group.indices.chunks(3) {
+            let (a, b, c) = (tri[0] + offset, tri[1] + offset, tri[2] + offset);
+            writeln!(obj, "f {0}/{0}/{0} {1}/{1}/{1} {2}/{2}/{2}", a, b, c).unwrap();
+        }
+        offset += group.positions.len() as u32;
+    }
+
+    std::fs::write(path, obj)?;
+    std::fs::write(&mtl_path, mtl)?;
+    Ok(())
+}
+
+// Buffer binario del .glb: cada bloque alineado a 4 bytes
+struct BinBuffer {
+    data: Vec<u8>,
+    views: Vec<Value>,
+}
+
+impl BinBuffer {
+    fn push(&mut self, bytes: &[u8], target: Option<u32>) -> usize {
+        while self.data.len() % 4 != 0 {
+            self.data.push(0);
+        }
+        let mut view = json!({
+            "buffer": 0,
+            "byteOffset": self.data.len(),
+            "byteLength": bytes.len(),
+        });
+        if let Some(target) = target {
+            view["target"] = json!(target);
+        }
+        self.data.extend_from_slice(bytes);
+        self.views.push(view);
+        self.views.len() - 1
+    }
+}
+
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+
+pub fn export_glb(scene: &Scene, library: &MaterialLibrary, lights: &[Light], path: &Path) -> Result<(), ExportError> {
+    let groups = collect_groups(scene, library);
+    let mut bin = BinBuffer { data: Vec::new(), views: Vec::new() };
+    let mut accessors: Vec<Value> = Vec::new();
+    let mut materials: Vec<Value> = Vec::new();
+    let mut primitives: Vec<Value> = Vec::new();
+    let mut images: Vec<Value> = Vec::new();
+    let mut textures: Vec<Value> = Vec::new();
+    let mut texture_index: HashMap<String, usize> = HashMap::new();
+
+    for group in &groups {
+        let m = &group.material;
+
+        // Texturas embebidas en el .glb
+        let texture = match &group.texture {
+            Some(path) => match texture_index.get(path) {
+                Some(index) => Some(*index),
+                None => {
+                    let bytes = std::fs::read(path).map_err(|e| ExportError(format!("{}: {}", path, e)))?;
+                    let mime = if path.ends_with(".jpg") || path.ends_with(".jpeg") { "image/jpeg" } else { "image/png" };
+                    let view = bin.push(&bytes, None);
+                    images.push(json!({ "bufferView": view, "mimeType": mime }));
+                    textures.push(json!({ "sampler": 0, "source": images.len() - 1 }));
+                    texture_index.insert(path.clone(), textures.len() - 1);
+                    Some(textures.len() - 1)
+                }
+            },
+            None => None,
+        };
+
+        let base_color = if texture.is_some() {
+            [1.0, 1.0, 1.0]
+        } else {
+            [
+                m.diffuse.r as f32 / 255.0,
+                m.diffuse.g as f32 / 255.0,
+                m.diffuse.b as f32 / 255.0,
+            ]
+        };
+        let (metallic, roughness) = match &m.pbr {
+            Some(pbr) => (pbr.metallic, pbr.roughness),
+            // Exponente de Phong -> rugosidad aproximada
+            None => (m.reflectivity, (2.0 / (m.specular + 2.0)).sqrt()),
+        };
+        let mut pbr = json!({
+            "baseColorFactor": [base_color[0], base_color[1], base_color[2], 1.0 - m.transparency],
+            "metallicFactor": metallic,
+            "roughnessFactor": roughness,
+        });
+        if let Some(texture) = texture {
+            pbr["baseColorTexture"] = json!({ "index": texture });
+        }
+        let mut material = json!({
+            "name": group.name,
+            "pbrMetallicRoughness": pbr,
+            "extensions": { "KHR_materials_ior": { "ior": m.refractive_index } },
+        });
+        if m.transparency > 0.0 {
+            material["alphaMode"] = json!("BLEND");
+        } else if m.alpha_test {
+            // Como en el render: los texels con alfa < 0.5 no existen y se ve el otro lado
+            material["alphaMode"] = json!("MASK");
+            material["alphaCutoff"] = json!(0.5);
+            material["doubleSided"] = json!(true);
+        }
+        if m.emission_strength > 0.0 {
+            material["emissiveFactor"] = json!([m.emission.x.min(1.0), m.emission.y.min(1.0), m.emission.z.min(1.0)]);
+            material["extensions"]["KHR_materials_emissive_strength"] = json!({ "emissiveStrength": m.emission_strength });
+            // cast_ray multiplica la emisión por el color de la textura
+            if let Some(texture) = texture {
+                material["emissiveTexture"] = json!({ "index": texture });
+            }
+        }
+        materials.push(material);
+
+        // Vértices: posición, normal y UV (glTF tiene v = 0 arriba)
+        let mut min = [f32::INFINITY; 3];
+        let mut max = [f32::NEG_INFINITY; 3];
+        let mut positions = Vec::with_capacity(group.positions.len() * 12);
+        for p in &group.positions {
+            for (i, c) in [p.x, p.y, p.z].into_iter().enumerate() {
+                min[i] = min[i].min(c);
+                max[i] = max[i].max(c);
+                positions.extend_from_slice(&c.to_le_bytes());
+            }
+        }
+        let normals: Vec<u8> = group
+            .normals
+            .iter()
+            .flat_map(|n| [n.x, n.y, n.z])
+            .flat_map(|c| c.to_le_bytes())
+            .collect();
+        let uvs: Vec<u8> = group
+            .uvs
+            .iter()
+            .flat_map(|(u, v)| [*u, 1.0 - v])
+            .flat_map(|c| c.to_le_bytes())
+            .collect();
+        let indices: Vec<u8> = group.indices.iter().flat_map(|i| i.to_le_bytes()).collect();
+
+        let count = group.positions.len();
+        let mut accessor = |view: usize, component: u32, count: usize, kind: &str| {
+            accessors.push(json!({ "bufferView": view, "componentType": component, "count": count, "type": kind }));
+            accessors.len() - 1
+        };
+        let position = accessor(bin.push(&positions, Some(ARRAY_BUFFER)), FLOAT, count, "VEC3");
+        let normal = accessor(bin.push(&normals, Some(ARRAY_BUFFER)), FLOAT, count, "VEC3");
+        let uv = accessor(bin.push(&uvs, Some(ARRAY_BUFFER)), FLOAT, count, "VEC2");
+        let index = accessor(
+            bin.push(&indices, Some(ELEMENT_ARRAY_BUFFER)),
+            UNSIGNED_INT,
+            group.indices.len(),
+            "SCALAR",
+        );
+        // POSITION necesita min/max
+        accessors[position]["min"] = json!(min);
+        accessors[position]["max"] = json!(max);
+
+        primitives.push(json!({
+            "attributes": { "POSITION": position, "NORMAL": normal, "TEXCOORD_0": uv },
+            "indices": index,
+            "material": materials.len() - 1,
+        }));
+    }
+
+    // Un nodo con toda la geometría y uno por luz
+    let mut nodes = Vec::new();
+    if !primitives.is_empty() {
+        nodes.push(json!({ "name": "Diorama", "mesh": 0 }));
+    }
+    let mut light_defs = Vec::new();
+    for (i, light) in lights.iter().enumerate() {
+        light_defs.push(json!({
+            "type": "point",
+            "color": [light.color.x, light.color.y, light.color.z],
+            "intensity": light.intensity,
+        }));
+        nodes.push(json!({
+            "name": format!("Luz {}", i),
+            "translation": [light.position.x, light.position.y, light.position.z],
+            "extensions": { "KHR_lights_punctual": { "light": i } },
+        }));
+    }
+
+    while bin.data.len() % 4 != 0 {
+        bin.data.push(0);
+    }
+    let mut extensions_used = vec!["KHR_lights_punctual", "KHR_materials_ior"];
+    if groups.iter().any(|g| g.material.emission_strength > 0.0) {
+        extensions_used.push("KHR_materials_emissive_strength");
+    }
+    let mut gltf = json!({
+        "asset": { "version": "2.0", "generator": "Graficas-diorama" },
+        "extensionsUsed": extensions_used,
+        "extensions": { "KHR_lights_punctual": { "lights": light_defs } },
+        "scene": 0,
+        "scenes": [{ "nodes": (0..nodes.len()).collect::<Vec<_>>() }],
+        "nodes": nodes,
+    });
+    if !primitives.is_empty() {
+        gltf["meshes"] = json!([{ "name": "Diorama", "primitives": primitives }]);
+        gltf["materials"] = json!(materials);
+        gltf["accessors"] = json!(accessors);
+        gltf["bufferViews"] = json!(bin.views);
+        gltf["buffers"] = json!([{ "byteLength": bin.data.len() }]);
+    }
+    if !textures.is_empty() {
+        // NEAREST para que se vean los píxeles como en Minecraft; REPEAT para el mosaico
+        gltf["samplers"] = json!([{ "magFilter": 9728, "minFilter": 9728, "wrapS": 10497, "wrapT": 10497 }]);
+        gltf["images"] = json!(images);
+        gltf["textures"] = json!(textures);
+    }
+
+    let mut json_bytes = serde_json::to_vec(&gltf).map_err(|e| ExportError(e.to_string()))?;
+    while json_bytes.len() % 4 != 0 {
+        json_bytes.push(b' ');
+    }
+
+    // Cabecera + chunk JSON + chunk BIN
+    let total = 12 + 8 + json_bytes.len() + 8 + bin.data.len();
+    let mut glb = Vec::with_capacity(total);
+    glb.extend_from_slice(b"glTF");
+    glb.extend_from_slice(&2u32.to_le_bytes());
+    glb.extend_from_slice(&(total as u32).to_le_bytes());
+    glb.extend_from_slice(&(json_bytes.len() as u32).to_le_bytes());
+    glb.extend_from_slice(b"JSON");
+    glb.extend_from_slice(&json_bytes);
+    glb.extend_from_slice(&(bin.data.len() as u32).to_le_bytes());
+    glb.extend_from_slice(b"BIN\0");
+    glb.extend_from_slice(&bin.data);
+
+    std::fs::write(path, glb)?;
+    Ok(())
+}
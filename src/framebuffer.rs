@@ -1,12 +1,19 @@
 use raylib::prelude::*;
+use rayon::prelude::*;
 
 pub struct Framebuffer{
     pub width:i32,
     pub height:i32,
-    pub color_buffer: Image, //imagen, literalmente
+    pub color_buffer: Image, //imagen, literalmente; se actualiza desde pixel_data una vez por cuadro
     background_color:Color,
     current_color:Color,
     pixel_data: Vec<Color>,
+    // Suma de las muestras de cada píxel mientras la cámara no cambia; pixel_data es el promedio
+    accumulation: Vec<Vector3>,
+    samples: u32,
+    accumulated_revision: Option<u64>,
+    // Imagen reducida (un color por bloque de píxeles) mientras la cámara se mueve
+    preview: Vec<Color>,
 }
 
 impl Framebuffer {
@@ -20,23 +27,97 @@ impl Framebuffer {
             color_buffer,
             background_color,
             current_color: Color::WHITE,
-            pixel_data
+            pixel_data,
+            accumulation: vec![Vector3::zero(); size],
+            samples: 0,
+            accumulated_revision: None,
+            preview: Vec::new(),
         }
     }
 
     pub fn clear(&mut self){
         self.pixel_data.fill(self.background_color);
-        self.color_buffer = Image::gen_image_color(self.width,self.height,self.background_color)
     }
 
     pub fn set_pixel(&mut self, x:i32, y:i32){
         if x >= 0 && x < self.width && y >= 0 && y < self.height {
             let index = (y * self.width + x) as usize;
             self.pixel_data[index] = self.current_color;
-            Image::draw_pixel(&mut self.color_buffer, x as i32, y as i32, self.current_color);
         }
     }
 
+    // Cuántas muestras lleva acumuladas cada píxel. Si la cámara cambió desde la
+    // última muestra se descartan y se empieza de nuevo.
+    pub fn begin_sample(&mut self, camera_revision: u64) -> u32 {
+        if self.accumulated_revision != Some(camera_revision) {
+            self.reset_accumulation();
+            self.accumulated_revision = Some(camera_revision);
+        }
+        self.samples
+    }
+
+    // Descarta las muestras (p. ej. al recargar la escena)
+    pub fn reset_accumulation(&mut self) {
+        self.accumulation.fill(Vector3::zero());
+        self.samples = 0;
+        self.accumulated_revision = None;
+    }
+
+    // Suma de las muestras fila por fila, para que render() le agregue una muestra por tiles
+    pub fn accumulation_mut(&mut self) -> &mut [Vector3] {
+        &mut self.accumulation
+    }
+
+    // Cierra la muestra que render() acaba de sumar y pasa el promedio a los píxeles
+    pub fn end_sample(&mut self) {
+        self.samples += 1;
+        let scale = 1.0 / self.samples as f32;
+        self.pixel_data.par_iter_mut().zip(&self.accumulation).for_each(|(pixel, sum)| {
+            let color = *sum * scale;
+            *pixel = Color::new(
+                (color.x.clamp(0.0, 1.0) * 255.0) as u8,
+                (color.y.clamp(0.0, 1.0) * 255.0) as u8,
+                (color.z.clamp(0.0, 1.0) * 255.0) as u8,
+                255,
+            );
+        });
+    }
+
+    // Tamaño de la imagen reducida con bloques de `block` x `block` píxeles
+    pub fn preview_size(&self, block: usize) -> (usize, usize) {
+        ((self.width as usize).div_ceil(block), (self.height as usize).div_ceil(block))
+    }
+
+    // Colores de la imagen reducida, fila por fila, para que render() los llene por tiles
+    pub fn preview_mut(&mut self, block: usize) -> &mut [Color] {
+        let (columns, rows) = self.preview_size(block);
+        self.preview.resize(columns * rows, self.background_color);
+        &mut self.preview
+    }
+
+    // Amplía la imagen reducida a la ventana repitiendo cada color en su bloque. Las
+    // muestras acumuladas ya no sirven: el próximo cuadro completo empieza de cero.
+    pub fn end_preview(&mut self, block: usize) {
+        let (columns, _) = self.preview_size(block);
+        let width = self.width as usize;
+        let preview = &self.preview;
+        self.pixel_data.par_chunks_mut(width).enumerate().for_each(|(y, row)| {
+            let source = &preview[(y / block) * columns..][..columns];
+            for (x, pixel) in row.iter_mut().enumerate() {
+                *pixel = source[x / block];
+            }
+        });
+        self.reset_accumulation();
+    }
+
+    // Copia todos los píxeles a la Image de raylib de una vez, en lugar de un draw_pixel por píxel
+    fn update_image(&mut self) {
+        // gen_image_color crea la imagen en RGBA de 8 bits, el mismo formato que Color
+        assert_eq!((self.color_buffer.width, self.color_buffer.height), (self.width, self.height));
+        let data = self.color_buffer.data as *mut Color;
+        unsafe { std::slice::from_raw_parts_mut(data, self.pixel_data.len()) }.copy_from_slice(&self.pixel_data);
+    }
+
     pub fn set_background_color(&mut self, color:Color){
         self.background_color = color;
         self.clear();
@@ -46,11 +127,14 @@ impl Framebuffer {
         self.current_color = color;
     }
 
-    pub fn render_to_file(&self, file_path:&str){
-        Image::export_image(&self.color_buffer, file_path);
+    // Devuelve false si raylib no pudo guardar la imagen
+    pub fn render_to_file(&mut self, file_path:&str) -> bool {
+        self.update_image();
+        Image::export_image(&self.color_buffer, file_path)
     }
 
-    pub fn swap_buffers(&self, window: &mut RaylibHandle, raylib_thread: &RaylibThread) {
+    pub fn swap_buffers(&mut self, window: &mut RaylibHandle, raylib_thread: &RaylibThread, message: Option<&str>) {
+        self.update_image();
         let texture = window
             .load_texture_from_image(raylib_thread, &self.color_buffer)
             .unwrap_or_else(|e| panic!("Failed to create texture from framebuffer: {}", e));
@@ -58,6 +142,17 @@ impl Framebuffer {
         let mut d = window.begin_drawing(raylib_thread);
         d.draw_texture(&texture, 0, 0, Color::WHITE);
 
+        // Mensaje encima de la imagen (p. ej. el error de una recarga fallida)
+        if let Some(message) = message {
+            let font_size = 16;
+            let max_chars = ((self.width - 20) / (font_size * 6 / 10)).max(1) as usize;
+            let lines = wrap_text(message, max_chars);
+            let height = lines.len() as i32 * (font_size + 4) + 16;
+            d.draw_rectangle(0, 0, self.width, height, Color::new(0, 0, 0, 200));
+            for (i, line) in lines.iter().enumerate() {
+                d.draw_text(line, 10, 8 + i as i32 * (font_size + 4), font_size, Color::new(255, 90, 90, 255));
+            }
+        }
     }
 
     pub fn get_pixel_color(&self, x: i32, y: i32) -> Option<Color> {
@@ -70,3 +165,22 @@ impl Framebuffer {
     }
 
 }
+
+// Parte el texto en líneas de a lo sumo `max_chars` caracteres
+fn wrap_text(text: &str, max_chars: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split(' ') {
+            if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > max_chars {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        lines.push(line);
+    }
+    lines
+}
@@ -0,0 +1,139 @@
+// import.rs
+// Piezas comunes a los importadores de construcciones de Minecraft:
+// la tabla de bloques -> materiales, el reporte de importación y la grilla.
+use raylib::prelude::*;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::material::MaterialId;
+use crate::materials::MaterialLibrary;
+use crate::nbt::NbtError;
+use crate::scene::Scene;
+
+// Cada bloque de Minecraft ocupa un cubo de 2x2x2 (half_size = 1), igual que en el diorama
+pub const BLOCK_SIZE: f32 = 2.0;
+
+#[derive(Debug)]
+pub struct ImportError(pub String);
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+impl From<std::io::Error> for ImportError {
+    fn from(e: std::io::Error) -> Self {
+        ImportError(e.to_string())
+    }
+}
+
+impl From<NbtError> for ImportError {
+    fn from(e: NbtError) -> Self {
+        ImportError(e.to_string())
+    }
+}
+
+// Resumen de lo que se importó y de los bloques que no tienen material
+#[derive(Default)]
+pub struct ImportReport {
+    pub placed: usize,
+    // Bloques que no se agregaron porque quedan tapados por todos lados
+    pub hidden: usize,
+    pub unmapped: BTreeMap<String, usize>,
+}
+
+impl ImportReport {
+    pub fn warnings(&self) -> Vec<String> {
+        self.unmapped
+            .iter()
+            .map(|(id, count)| format!("bloque sin material: {} ({} veces)", id, count))
+            .collect()
+    }
+}
+
+// Quita el namespace y las propiedades: "minecraft:furnace[facing=north]" -> "furnace"
+pub fn block_name(block_id: &str) -> &str {
+    let id = block_id.split('[').next().unwrap_or(block_id);
+    id.strip_prefix("minecraft:").unwrap_or(id)
+}
+
+pub fn is_air(block_id: &str) -> bool {
+    matches!(block_name(block_id), "air" | "cave_air" | "void_air" | "structure_void")
+}
+
+// Bloques a través de los que se ve (no tapan a sus vecinos)
+pub fn is_transparent(block_id: &str) -> bool {
+    let name = block_name(block_id);
+    is_air(block_id)
+        || name.contains("glass")
+        || name.ends_with("leaves")
+        || matches!(name, "water" | "lava" | "ice" | "barrier")
+}
+
+// Material por defecto para bloques que la biblioteca no asocia a ningún material
+pub fn material_for_block(block_id: &str) -> Option<&'static str> {
+    let name = match block_name(block_id) {
+        "furnace" | "blast_furnace" | "smoker" => "horno",
+        "oak_planks" | "birch_planks" | "spruce_planks" => "madera",
+        "bookshelf" => "libreria",
+        "azalea_leaves" | "flowering_azalea_leaves" => "flores",
+        "diamond_ore" | "deepslate_diamond_ore" => "diamante",
+        "diorite" | "polished_diorite" => "diorita",
+        "stone" | "cobblestone" | "stone_bricks" => "roca",
+        "granite" | "polished_granite" => "granito",
+        "grass_block" | "dirt_path" | "grass_path" => "dirt_grass",
+        "bricks" => "brick",
+        "sand" => "sand",
+        "water" => "water",
+        "dirt" | "coarse_dirt" | "rooted_dirt" => "dirt",
+        "iron_ore" | "deepslate_iron_ore" => "hierro",
+        "dark_oak_planks" => "madera_oscura",
+        "redstone_lamp" => "redstone_lamp",
+        "note_block" => "musical",
+        "pumpkin" | "carved_pumpkin" | "jack_o_lantern" => "calabaza",
+        _ => return None,
+    };
+    Some(name)
+}
+
+// Centro en espacio mundo del bloque (x, y, z) de la construcción
+pub fn block_center(origin: Vector3, x: i32, y: i32, z: i32) -> Vector3 {
+    Vector3::new(
+        origin.x + x as f32 * BLOCK_SIZE,
+        origin.y + y as f32 * BLOCK_SIZE,
+        origin.z + z as f32 * BLOCK_SIZE,
+    )
+}
+
+// Agrega un bloque unitario a la escena
+pub fn add_block(scene: &mut Scene, center: Vector3, material: MaterialId) {
+    let half = BLOCK_SIZE * 0.5;
+    scene.add_rectangle(center, Vector3::new(half, half, half), material);
+}
+
+// Coloca un bloque por su id: ignora el aire y anota en el reporte los que no tienen material
+pub fn place_block(
+    scene: &mut Scene,
+    materials: &MaterialLibrary,
+    report: &mut ImportReport,
+    block_id: &str,
+    center: Vector3,
+) {
+    if is_air(block_id) {
+        return;
+    }
+    // Primero el material cuyo `block` es este bloque; si no, la tabla de equivalencias
+    let material = materials
+        .for_block(block_id)
+        .or_else(|| material_for_block(block_id).and_then(|name| materials.id(name)));
+    match material {
+        Some(material) => {
+            add_block(scene, center, material);
+            report.placed += 1;
+        }
+        None => *report.unmapped.entry(block_name(block_id).to_string()).or_insert(0) += 1,
+    }
+}
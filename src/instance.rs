@@ -0,0 +1,95 @@
+// instance.rs
+// Instancias: un prototipo (un cubo, un modelo de varias cajas o una escena entera como un
+// árbol) se guarda una sola vez y cada instancia solo agrega su transformación. Para
+// intersectar, el rayo se lleva al espacio del prototipo y el impacto se devuelve a mundo.
+use raylib::prelude::*;
+use std::sync::Arc;
+
+use crate::bvh::Aabb;
+use crate::cube::Cube;
+use crate::ray_intersect::{HitInfo, RayIntersect};
+use crate::scene::Scene;
+
+// Escala uniforme, giro alrededor de Y y traslación (en ese orden). Con solo giro en Y un
+// cubo del prototipo sigue siendo un Cube (rot_x, rot_y) al exportarlo
+#[derive(Clone, Copy)]
+pub struct Transform {
+    pub translation: Vector3,
+    // Radianes, como rot_y de Cube
+    pub rot_y: f32,
+    pub scale: f32,
+}
+
+impl Transform {
+    // Punto del prototipo -> mundo
+    pub fn to_world(self, p: Vector3) -> Vector3 {
+        Cube::rotate_y(p * self.scale, self.rot_y) + self.translation
+    }
+
+    // Punto en mundo -> prototipo
+    pub fn to_local(self, p: Vector3) -> Vector3 {
+        Cube::rotate_y(p - self.translation, -self.rot_y) / self.scale
+    }
+}
+
+pub struct Instance {
+    pub prototype: Arc<Scene>,
+    pub transform: Transform,
+    // Caja en mundo, calculada una vez al crear la instancia
+    bounds: Aabb,
+}
+
+impl Instance {
+    pub fn new(prototype: Arc<Scene>, transform: Transform) -> Self {
+        let local = prototype.bounds();
+        let mut bounds = Aabb::empty();
+        // Un prototipo vacío deja la caja vacía
+        if local.min.x <= local.max.x {
+            for x in [local.min.x, local.max.x] {
+                for y in [local.min.y, local.max.y] {
+                    for z in [local.min.z, local.max.z] {
+                        bounds.grow(transform.to_world(Vector3::new(x, y, z)));
+                    }
+                }
+            }
+        }
+        Instance { prototype, transform, bounds }
+    }
+}
+
+impl RayIntersect for Instance {
+    fn ray_intersect(&self, ray_origin: &Vector3, ray_direction: &Vector3) -> Option<HitInfo> {
+        // La dirección solo se gira (sigue unitaria); las distancias del prototipo quedan
+        // divididas por la escala
+        let local_origin = self.transform.to_local(*ray_origin);
+        let local_direction = Cube::rotate_y(*ray_direction, -self.transform.rot_y);
+        let mut hit = self.prototype.closest_hit(&local_origin, &local_direction)?;
+
+        // local_point y local_normal quedan en el espacio del objeto del prototipo (caras y UVs)
+        hit.point = self.transform.to_world(hit.point);
+        hit.normal = Cube::rotate_y(hit.normal, self.transform.rot_y);
+        hit.distance *= self.transform.scale;
+        Some(hit)
+    }
+
+    fn aabb(&self) -> Aabb {
+        self.bounds
+    }
+
+    // Los cubos del prototipo ya ubicados en mundo (el exportador no conoce las instancias)
+    fn for_each_cube(&self, f: &mut dyn FnMut(&Cube)) {
+        let t = self.transform;
+        for object in &self.prototype.objects {
+            object.for_each_cube(&mut |cube| {
+                f(&Cube {
+                    center: t.to_world(cube.center),
+                    half_size: cube.half_size * t.scale,
+                    rot_x: cube.rot_x,
+                    rot_y: cube.rot_y + t.rot_y,
+                    material: cube.material,
+                    uv_offset: cube.uv_offset * t.scale,
+                });
+            });
+        }
+    }
+}
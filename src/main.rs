@@ -17,41 +17,56 @@ mod textures;
 mod scene;
 mod materials;
 mod skybox;
+mod scene_file;
+mod nbt;
+mod import;
+mod structure;
+mod schematic;
+mod vox;
+mod resource_pack;
+mod anvil;
+mod watcher;
+mod export;
+mod bvh;
+mod bench;
+mod voxel_grid;
+mod svo;
+mod tiles;
+mod packet;
+mod resolution;
+mod instance;
+mod optimize;
 
 use skybox::Skybox;
 use framebuffer::Framebuffer;
 use ray_intersect::{RayIntersect, HitInfo};
 use cube::Cube;
-use material::Material;
+use material::{Material, Pbr, TextureId};
 use camera::Camera;
 use light::Light;
 use textures::TextureManager;
 use material::TextureFaces;
-use crate::materials::Materials;
+use crate::materials::MaterialLibrary;
 use crate::scene::Scene;
-
-// Util para comprobar si hay cualquier intersección entre origin y origin + dir*max_dist
-fn intersects_any(
-    origin: &Vector3,
-    direction: &Vector3,
-    objects: &[&dyn RayIntersect],
-    max_dist: f32,
-) -> bool {
-    for obj in objects {
-        if let Some(hit) = obj.ray_intersect(origin, direction) {
-            if hit.distance < max_dist {
-                return true;
-            }
-        }
-    }
-    false
-}
+use crate::scene_file::LoadedScene;
+use std::path::{Path, PathBuf};
+use crate::resource_pack::ResourcePack;
+use crate::watcher::FileWatcher;
+use crate::tiles::{TileConfig, TileOrder};
+use crate::resolution::ResolutionController;
+use crate::packet::{RayPacket, LANES};
+use std::time::{Duration, Instant};
+
+// Cuenta las reservas de memoria para --bench, solo si se compila con `--features count-allocs`
+#[cfg(feature = "count-allocs")]
+#[global_allocator]
+static GLOBAL: bench::CountingAlloc = bench::CountingAlloc;
 
 fn reflect(i: &Vector3, n: &Vector3) -> Vector3 {
     *i - *n * 2.0 * i.dot(*n)
 }
 
-pub fn refract(incident: &Vector3, normal: &Vector3, refractive_index: f32) -> Vector3 {
+pub fn refract(incident: &Vector3, normal: &Vector3, refractive_index: f32) -> Option<Vector3> {
     // Implementation of Snell's Law for refraction.
     // It calculates the direction of a ray as it passes from one medium to another.
 
@@ -86,11 +101,28 @@ pub fn refract(incident: &Vector3, normal: &Vector3, refractive_index: f32) -> V
     if k < 0.0 {
         // If k is negative, it means total internal reflection has occurred.
         // There is no refracted ray, so we return None.
-        Vector3::zero()
+        None
     } else {
         // If k is non-negative, we can calculate the direction of the refracted ray.
-        *incident * eta + n * (eta * cosi - k.sqrt())
+        Some(*incident * eta + n * (eta * cosi - k.sqrt()))
+    }
+}
+
+// Fresnel exacto de un dieléctrico (luz sin polarizar): fracción que se refleja al pasar
+// entre el aire y un medio con ese índice, en cualquiera de los dos sentidos. Vale 1 con
+// reflexión total interna
+pub fn fresnel(incident: &Vector3, normal: &Vector3, refractive_index: f32) -> f32 {
+    let cosi = incident.dot(*normal).clamp(-1.0, 1.0);
+    let (etai, etat) = if cosi > 0.0 { (refractive_index, 1.0) } else { (1.0, refractive_index) };
+    let sint = etai / etat * (1.0 - cosi * cosi).max(0.0).sqrt();
+    if sint >= 1.0 {
+        return 1.0;
     }
+    let cost = (1.0 - sint * sint).max(0.0).sqrt();
+    let cosi = cosi.abs();
+    let rs = (etat * cosi - etai * cost) / (etat * cosi + etai * cost);
+    let rp = (etai * cosi - etat * cost) / (etai * cosi + etat * cost);
+    (rs * rs + rp * rp) * 0.5
 }
 
 
@@ -134,151 +166,411 @@ fn uv_repeat(coord: f32, half_size: f32) -> f32 {
     if c < 0.0 { c + 1.0 } else { c }
 }
 
-// firma actualizada: ahora recibe texture_manager: &TextureManager
-pub fn cast_ray(
+// Todo lo que cast_ray necesita de la escena cargada; los HitInfo solo traen
+// el MaterialId, así que el material y sus texturas se buscan acá
+pub struct RenderContext<'a> {
+    pub scene: &'a Scene,
+    pub lights: &'a [Light],
+    pub materials: &'a MaterialLibrary,
+    pub texture_manager: &'a TextureManager,
+    pub skybox: &'a Skybox,
+    // Segundos desde el inicio, para el cuadro de las texturas animadas
+    pub time: f32,
+}
+
+// Textura de la cara golpeada y (u, v) del impacto, si el material tiene texturas
+fn texture_uv(hit: &HitInfo, m: &Material) -> Option<(TextureId, f32, f32)> {
+    let face = *m.textures.as_ref()?.face(hit.local_normal);
+
+    // ← TODAS las llamadas con 1.0
+    let (u, v) = if hit.local_normal.y.abs() > 0.9 {
+        (uv_repeat(hit.local_point.x, 1.0), uv_repeat(hit.local_point.z, 1.0))
+    } else if hit.local_normal.x.abs() > 0.9 {
+        (uv_repeat(hit.local_point.z, 1.0), uv_repeat(hit.local_point.y, 1.0))
+    } else {
+        (uv_repeat(hit.local_point.x, 1.0), uv_repeat(hit.local_point.y, 1.0))
+    };
+
+    Some((face, u, v))
+}
+
+// Color de la textura de la cara golpeada, si el material tiene texturas
+fn texture_color(hit: &HitInfo, m: &Material, ctx: &RenderContext) -> Option<Vector3> {
+    let (face, u, v) = texture_uv(hit, m)?;
+    Some(ctx.texture_manager.sample_uv(face, u, v, ctx.time))
+}
+
+// Caras recortadas (alpha test) que un rayo puede atravesar; si después sigue cayendo en
+// texels transparentes se toma como que no tocó nada
+const MAX_ALPHA_SKIPS: usize = 32;
+
+// ¿El impacto cae en un texel transparente de un material con alpha test?
+fn alpha_cut(hit: &HitInfo, ctx: &RenderContext) -> bool {
+    let m = &ctx.materials[hit.material];
+    m.alpha_test
+        && texture_uv(hit, m).is_some_and(|(face, u, v)| ctx.texture_manager.sample_alpha(face, u, v, ctx.time) < 0.5)
+}
+
+// closest_hit que sigue de largo por los texels transparentes (el BVH no conoce las
+// texturas). La distancia es desde `origin`
+fn closest_opaque_hit(origin: &Vector3, direction: &Vector3, ctx: &RenderContext) -> Option<HitInfo> {
+    let mut hit = ctx.scene.closest_hit(origin, direction)?;
+    let mut skips = 0;
+    while alpha_cut(&hit, ctx) {
+        if skips == MAX_ALPHA_SKIPS {
+            return None;
+        }
+        skips += 1;
+        hit = ctx.scene.closest_hit(&(hit.point + *direction * 1e-3), direction)?;
+    }
+    hit.distance = (hit.point - *origin).length();
+    Some(hit)
+}
+
+// ¿Lo que tapa el rayo de sombra es opaco? Si cayó en un texel recortado se buscan los
+// impactos uno por uno hasta la luz
+fn shadow_blocked_by(hit: Option<HitInfo>, origin: &Vector3, direction: &Vector3, max_dist: f32, ctx: &RenderContext) -> bool {
+    match hit {
+        None => false,
+        Some(hit) if !alpha_cut(&hit, ctx) => true,
+        Some(_) => closest_opaque_hit(origin, direction, ctx).is_some_and(|hit| hit.distance < max_dist),
+    }
+}
+
+// Lo que el sombreado necesita de un impacto, antes de sumar las luces
+struct Surface<'a> {
+    point: Vector3,
+    normal: Vector3,
+    view_dir: Vector3,
+    base_color: Vector3,
+    m: &'a Material,
+    // Medio por el que viajaba el rayo hasta acá (None = aire)
+    medium: Option<&'a Material>,
+}
+
+// Color final si el impacto es emisivo; si no, la superficie para sumarle las luces
+fn surface<'a>(
     ray_origin: &Vector3,
+    hit: &HitInfo,
+    medium: Option<&'a Material>,
+    ctx: &RenderContext<'a>,
+) -> Result<Surface<'a>, Vector3> {
+    let m = &ctx.materials[hit.material];
+
+    // Color base desde material, o desde la textura si tiene
+    let base_color = texture_color(hit, m, ctx).unwrap_or_else(|| {
+        Vector3::new(
+            m.diffuse.r as f32 / 255.0,
+            m.diffuse.g as f32 / 255.0,
+            m.diffuse.b as f32 / 255.0,
+        )
+    });
+
+    // Si el material es emisivo, retorna su emisión directamente
+    if m.emission_strength > 0.0 {
+        return Err(base_color * m.emission * m.emission_strength);
+    }
+
+    // Código para materiales no emisivos
+    let view_dir = (*ray_origin - hit.point).normalized();
+    Ok(Surface { point: hit.point, normal: hit.normal, view_dir, base_color, m, medium })
+}
+
+// Rayo de sombra desde la superficie hacia la luz: (origen, dirección, distancia máxima)
+fn shadow_ray(surface: &Surface, light: &Light) -> (Vector3, Vector3, f32) {
+    let light_dir = (light.position - surface.point).normalized();
+    let shadow_origin = surface.point + surface.normal * 1e-3;
+    let light_distance = (light.position - surface.point).length();
+    (shadow_origin, light_dir, light_distance - 1e-3)
+}
+
+// Aporte difuso y especular de una luz
+fn light_contribution(surface: &Surface, light: &Light, light_dir: Vector3, in_shadow: bool) -> (Vector3, Vector3) {
+    let shadow_intensity = if in_shadow { 0.8 } else { 0.0 };
+    let light_intensity = light.intensity * (1.0 - shadow_intensity);
+    if let Some(pbr) = &surface.m.pbr {
+        return ggx_contribution(surface, pbr, light_dir, light.color * light_intensity);
+    }
+
+    // Diffuse de esta luz
+    let diffuse_intensity = surface.normal.dot(light_dir).max(0.0) * light_intensity;
+    let diffuse = surface.base_color * light.color * diffuse_intensity;
+
+    // Specular de esta luz
+    let reflect_dir = reflect(&-light_dir, &surface.normal).normalized();
+    let specular_intensity = surface.view_dir.dot(reflect_dir).max(0.0).powf(surface.m.specular) * light_intensity;
+    (diffuse, light.color * specular_intensity)
+}
+
+// Orígenes del rayo reflejado (del lado por el que llegó el rayo) y del refractado (del
+// otro lado). La normal apunta hacia afuera del cubo aunque el rayo venga desde adentro
+fn bounce_origins(surface: &Surface, ray_direction: &Vector3) -> (Vector3, Vector3) {
+    let side = if ray_direction.dot(surface.normal) < 0.0 { surface.normal } else { -surface.normal };
+    (surface.point + side * 1e-3, surface.point - side * 1e-3)
+}
+
+// Medio en el que sigue el rayo refractado: el material si entra al cubo, aire si sale
+fn refracted_medium<'a>(surface: &Surface<'a>, ray_direction: &Vector3) -> Option<&'a Material> {
+    if ray_direction.dot(surface.normal) < 0.0 { Some(surface.m) } else { None }
+}
+
+// Beer-Lambert: fracción de cada canal que llega después de recorrer `distance` dentro del medio
+fn transmittance(medium: &Material, distance: f32) -> Vector3 {
+    let a = medium.absorption;
+    Vector3::new((-a.x * distance).exp(), (-a.y * distance).exp(), (-a.z * distance).exp())
+}
+
+// Reflectancia de frente: la del dieléctrico para la parte no metálica, el color base
+// para la metálica
+fn pbr_f0(surface: &Surface, pbr: &Pbr) -> Vector3 {
+    Vector3::new(pbr.f0, pbr.f0, pbr.f0) * (1.0 - pbr.metallic) + surface.base_color * pbr.metallic
+}
+
+// Fresnel de Schlick
+fn schlick(f0: Vector3, cos: f32) -> Vector3 {
+    f0 + (Vector3::new(1.0, 1.0, 1.0) - f0) * (1.0 - cos.clamp(0.0, 1.0)).powi(5)
+}
+
+// GGX (Trowbridge-Reitz) con sombreado de Smith y Fresnel de Schlick. Las luces están en
+// las unidades del Phong (difuso = color * N·L * intensidad), por eso el especular va por π
+fn ggx_contribution(surface: &Surface, pbr: &Pbr, light_dir: Vector3, radiance: Vector3) -> (Vector3, Vector3) {
+    let n = surface.normal;
+    let n_dot_l = n.dot(light_dir);
+    if n_dot_l <= 0.0 {
+        return (Vector3::zero(), Vector3::zero());
+    }
+    let n_dot_v = n.dot(surface.view_dir).max(1e-4);
+    let half = (light_dir + surface.view_dir).normalized();
+    let n_dot_h = n.dot(half).max(0.0);
+
+    // Con rugosidad 0 la distribución es una delta: se deja un mínimo para que el brillo se vea
+    let alpha = (pbr.roughness * pbr.roughness).max(1e-3);
+    let a2 = alpha * alpha;
+    let denom = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    let d = a2 / (PI * denom * denom);
+    let g1 = |x: f32| 2.0 * x / (x + (a2 + (1.0 - a2) * x * x).sqrt());
+    let g = g1(n_dot_l) * g1(n_dot_v);
+    let f = schlick(pbr_f0(surface, pbr), surface.view_dir.dot(half));
+
+    // D·G·F / (4 N·L N·V) por N·L
+    let specular = f * radiance * (PI * d * g / (4.0 * n_dot_v));
+    let kd = (Vector3::new(1.0, 1.0, 1.0) - f) * (1.0 - pbr.metallic);
+    let diffuse = surface.base_color * kd * radiance * n_dot_l;
+    (diffuse, specular)
+}
+
+// Como finish_surface para un material PBR: el reflejo se pesa con Fresnel en lugar de
+// `reflectivity` y la luz que no se refleja es la que puede refractarse
+fn finish_pbr(
+    surface: &Surface,
+    pbr: &Pbr,
     ray_direction: &Vector3,
-    objects: &[&dyn RayIntersect],
-    lights: &[Light],
+    total_diffuse: Vector3,
+    total_specular: Vector3,
+    ctx: &RenderContext,
     depth: u32,
-    texture_manager: &TextureManager,
-    skybox: &Skybox,
 ) -> Vector3 {
-    if depth > 3 {
-        return skybox.sample(*ray_direction, texture_manager);
+    let m = surface.m;
+    let edge = 1.0 - pbr.roughness;
+    let reflectance = if m.transparency > 0.0 {
+        // Dieléctrico: Fresnel exacto con su índice de refracción, también desde adentro
+        let kr = fresnel(ray_direction, &surface.normal, m.refractive_index);
+        Vector3::new(kr, kr, kr)
+    } else {
+        // Schlick con rugosidad: las superficies ásperas no llegan a espejo en ángulos rasantes
+        let n_dot_v = surface.normal.dot(surface.view_dir).max(0.0);
+        let f0 = pbr_f0(surface, pbr);
+        let f_max = Vector3::new(edge.max(f0.x), edge.max(f0.y), edge.max(f0.z));
+        f0 + (f_max - f0) * (1.0 - n_dot_v).powi(5)
+    };
+    let (reflect_origin, refract_origin) = bounce_origins(surface, ray_direction);
+
+    // Un solo rayo de reflejo es un espejo; en las superficies ásperas el reflejo real se
+    // desparrama, así que se atenúa en lugar de trazar muchos rayos
+    let gloss = edge * edge;
+    let reflection_weight = reflectance * gloss;
+    let mut color = total_diffuse * (1.0 - m.transparency) + total_specular;
+    if reflection_weight.x.max(reflection_weight.y).max(reflection_weight.z) > 0.01 {
+        let rdir = reflect(ray_direction, &surface.normal).normalized();
+        color = color + cast_ray_in(&reflect_origin, &rdir, surface.medium, ctx, depth + 1) * reflection_weight;
     }
 
-    let mut closest_hit: Option<HitInfo> = None;
-    for object in objects {
-        if let Some(hit) = object.ray_intersect(ray_origin, ray_direction) {
-            if closest_hit.is_none() || hit.distance < closest_hit.as_ref().unwrap().distance {
-                closest_hit = Some(hit);
-            }
-        }
+    // Con reflexión total interna `reflectance` es 1 y no se refracta nada
+    if m.transparency > 0.0
+        && let Some(refr) = refract(ray_direction, &surface.normal, m.refractive_index)
+    {
+        let transmitted = (Vector3::new(1.0, 1.0, 1.0) - reflectance) * m.transparency;
+        let medium = refracted_medium(surface, ray_direction);
+        color = color + cast_ray_in(&refract_origin, &refr.normalized(), medium, ctx, depth + 1) * transmitted;
     }
+    color
+}
 
-    if let Some(hit) = closest_hit {
-        let m = hit.material;
-
-        // Si el material es emisivo, retorna su emisión directamente
-        if m.emission_strength > 0.0 {
-            let mut base_color = Vector3::new(
-                m.diffuse.r as f32 / 255.0,
-                m.diffuse.g as f32 / 255.0,
-                m.diffuse.b as f32 / 255.0,
-            );
-
-            if let Some(tex_faces) = &m.texture_path {
-                let face_path = if hit.local_normal.y > 0.9 {
-                    &tex_faces.top
-                } else if hit.local_normal.y < -0.9 {
-                    &tex_faces.bottom
-                } else if hit.local_normal.x.abs() > 0.9 {
-                    &tex_faces.side_x
-                } else {
-                    &tex_faces.side_z
-                };
-
-                // ← TODAS las llamadas con 1.0
-                let (u, v) = if hit.local_normal.y.abs() > 0.9 {
-                    (uv_repeat(hit.local_point.x, 1.0), uv_repeat(hit.local_point.z, 1.0))
-                } else if hit.local_normal.x.abs() > 0.9 {
-                    (uv_repeat(hit.local_point.z, 1.0), uv_repeat(hit.local_point.y, 1.0))
-                } else {
-                    (uv_repeat(hit.local_point.x, 1.0), uv_repeat(hit.local_point.y, 1.0))
-                };
-
-                let color = texture_manager.sample_uv(face_path, u, v);
-                base_color = color;
-            }
+// Suma la luz directa ya calculada con la reflexión y la refracción (rayo por rayo)
+fn finish_surface(
+    surface: &Surface,
+    ray_direction: &Vector3,
+    total_diffuse: Vector3,
+    total_specular: Vector3,
+    ctx: &RenderContext,
+    depth: u32,
+) -> Vector3 {
+    let m = surface.m;
+    if let Some(pbr) = &m.pbr {
+        return finish_pbr(surface, pbr, ray_direction, total_diffuse, total_specular, ctx, depth);
+    }
 
-            return base_color * m.emission * m.emission_strength;
-        }
+    // Un dieléctrico reparte `reflectivity + transparency` entre reflejo y refracción según
+    // Fresnel: de frente pasa casi todo y en ángulos rasantes (o con reflexión total
+    // interna) se refleja
+    let (reflectivity, transparency) = if m.transparency > 0.0 {
+        let kr = fresnel(ray_direction, &surface.normal, m.refractive_index);
+        let weight = m.reflectivity + m.transparency;
+        (weight * kr, weight * (1.0 - kr))
+    } else {
+        (m.reflectivity, 0.0)
+    };
+    let (reflect_origin, refract_origin) = bounce_origins(surface, ray_direction);
 
-        // Código para materiales no emisivos
-        let view_dir = (*ray_origin - hit.point).normalized();
+    // Reflection
+    let mut reflection_color = ctx.skybox.sample(*ray_direction, ctx.texture_manager);
+    if reflectivity > 0.0 {
+        let rdir = reflect(ray_direction, &surface.normal).normalized();
+        reflection_color = cast_ray_in(&reflect_origin, &rdir, surface.medium, ctx, depth + 1);
+    }
 
-        // Color base desde material
-        let mut base_color = Vector3::new(
-            m.diffuse.r as f32 / 255.0,
-            m.diffuse.g as f32 / 255.0,
-            m.diffuse.b as f32 / 255.0,
-        );
-
-        // Aplica textura
-        if let Some(tex_faces) = &m.texture_path {
-            let face_path = if hit.local_normal.y > 0.9 {
-                &tex_faces.top
-            } else if hit.local_normal.y < -0.9 {
-                &tex_faces.bottom
-            } else if hit.local_normal.x.abs() > 0.9 {
-                &tex_faces.side_x
-            } else {
-                &tex_faces.side_z
-            };
-
-            // ← TODAS las llamadas con 1.0
-            let (u, v) = if hit.local_normal.y.abs() > 0.9 {
-                (uv_repeat(hit.local_point.x, 1.0), uv_repeat(hit.local_point.z, 1.0))
-            } else if hit.local_normal.x.abs() > 0.9 {
-                (uv_repeat(hit.local_point.z, 1.0), uv_repeat(hit.local_point.y, 1.0))
-            } else {
-                (uv_repeat(hit.local_point.x, 1.0), uv_repeat(hit.local_point.y, 1.0))
-            };
-
-            let color = texture_manager.sample_uv(face_path, u, v);
-            base_color = color;
-        }
+    let mut refraction_color = Vector3::zero();
+    if transparency > 0.0
+        && let Some(refr) = refract(ray_direction, &surface.normal, m.refractive_index)
+    {
+        let medium = refracted_medium(surface, ray_direction);
+        refraction_color = cast_ray_in(&refract_origin, &refr.normalized(), medium, ctx, depth + 1);
+    }
+
+    total_diffuse * m.albedo[0]
+        + total_specular * m.albedo[1]
+        + reflection_color * reflectivity
+        + refraction_color * transparency
+}
+
+// Sombreado de un impacto con sombras rayo por rayo
+fn shade_hit(
+    ray_origin: &Vector3,
+    ray_direction: &Vector3,
+    hit: &HitInfo,
+    medium: Option<&Material>,
+    ctx: &RenderContext,
+    depth: u32,
+) -> Vector3 {
+    let surface = match surface(ray_origin, hit, medium, ctx) {
+        Ok(surface) => surface,
+        Err(emission) => return emission,
+    };
+
+    // ¡ACUMULA ILUMINACIÓN DE TODAS LAS LUCES!
+    let mut total_diffuse = Vector3::zero();
+    let mut total_specular = Vector3::zero();
+    for light in ctx.lights {
+        let (shadow_origin, light_dir, max_dist) = shadow_ray(&surface, light);
+        let blocker = ctx.scene.first_hit(&shadow_origin, &light_dir, max_dist);
+        let in_shadow = shadow_blocked_by(blocker, &shadow_origin, &light_dir, max_dist, ctx);
+        let (diffuse, specular) = light_contribution(&surface, light, light_dir, in_shadow);
+        total_diffuse = total_diffuse + diffuse;
+        total_specular = total_specular + specular;
+    }
+
+    finish_surface(&surface, ray_direction, total_diffuse, total_specular, ctx, depth)
+}
+
+pub fn cast_ray(ray_origin: &Vector3, ray_direction: &Vector3, ctx: &RenderContext, depth: u32) -> Vector3 {
+    cast_ray_in(ray_origin, ray_direction, None, ctx, depth)
+}
 
-        // ¡ACUMULA ILUMINACIÓN DE TODAS LAS LUCES!
-        let mut total_diffuse = Vector3::zero();
-        let mut total_specular = Vector3::zero();
-
-        for light in lights {
-            let light_dir = (light.position - hit.point).normalized();
-            
-            // Sombra para esta luz
-            let shadow_origin = hit.point + hit.normal * 1e-3;
-            let light_distance = (light.position - hit.point).length();
-            let in_shadow = intersects_any(&shadow_origin, &light_dir, objects, light_distance - 1e-3);
-            
-            let shadow_intensity = if in_shadow { 0.8 } else { 0.0 };
-            let light_intensity = light.intensity * (1.0 - shadow_intensity);
-
-            // Diffuse de esta luz
-            let diffuse_intensity = hit.normal.dot(light_dir).max(0.0) * light_intensity;
-            total_diffuse = total_diffuse + (base_color * light.color * diffuse_intensity);
-
-            // Specular de esta luz
-            let reflect_dir = reflect(&-light_dir, &hit.normal).normalized();
-            let specular_intensity = view_dir.dot(reflect_dir).max(0.0).powf(m.specular) * light_intensity;
-            total_specular = total_specular + (light.color * specular_intensity);
+// cast_ray para un rayo que viaja dentro de `medium`: lo que se ve en el impacto llega
+// atenuado por lo que el medio absorbe en el camino
+fn cast_ray_in(
+    ray_origin: &Vector3,
+    ray_direction: &Vector3,
+    medium: Option<&Material>,
+    ctx: &RenderContext,
+    depth: u32,
+) -> Vector3 {
+    if depth > 3 {
+        return ctx.skybox.sample(*ray_direction, ctx.texture_manager);
+    }
+
+    match closest_opaque_hit(ray_origin, ray_direction, ctx) {
+        Some(hit) => {
+            let color = shade_hit(ray_origin, ray_direction, &hit, medium, ctx, depth);
+            match medium {
+                Some(medium) if medium.absorption != Vector3::zero() => color * transmittance(medium, hit.distance),
+                _ => color,
+            }
         }
+        None => ctx.skybox.sample(*ray_direction, ctx.texture_manager),
+    }
+}
 
-        // Reflection
-        let mut reflection_color = skybox.sample(*ray_direction, texture_manager);
-        if m.reflectivity > 0.0 {
-            let rdir = reflect(ray_direction, &hit.normal).normalized();
-            let rorigin = hit.point + hit.normal * 1e-3;
-            reflection_color = cast_ray(&rorigin, &rdir, objects, lights, depth + 1, texture_manager, skybox);
+// cast_ray para un paquete de rayos primarios vecinos: el impacto y las sombras de cada
+// luz se buscan con el paquete completo; la reflexión y la refracción, que ya no van
+// juntas, siguen rayo por rayo. Los carriles apagados quedan en negro.
+pub fn cast_packet(origin: Vector3, directions: [Vector3; LANES], active: [bool; LANES], ctx: &RenderContext) -> [Vector3; LANES] {
+    let packet = RayPacket::new([origin; LANES], directions, active);
+    let mut hits = ctx.scene.closest_hit_packet(&packet);
+    // Los carriles que cayeron en un texel recortado siguen solos
+    for lane in 0..LANES {
+        if hits[lane].as_ref().is_some_and(|hit| alpha_cut(hit, ctx)) {
+            hits[lane] = closest_opaque_hit(&origin, &directions[lane], ctx);
         }
+    }
 
-        let mut refraction_color = Vector3::zero();
-        if m.transparency > 0.0 {
-            let refr = refract(ray_direction, &hit.normal, m.refractive_index).normalized();
-            let rorigin = hit.point - hit.normal * 1e-3;
-            refraction_color = cast_ray(&rorigin, &refr, objects, lights, depth + 1, texture_manager, skybox);
+    let mut colors = [Vector3::zero(); LANES];
+    let mut surfaces: [Option<Surface>; LANES] = Default::default();
+    for lane in (0..LANES).filter(|&lane| active[lane]) {
+        match &hits[lane] {
+            None => colors[lane] = ctx.skybox.sample(directions[lane], ctx.texture_manager),
+            Some(hit) => match surface(&origin, hit, None, ctx) {
+                Ok(surface) => surfaces[lane] = Some(surface),
+                Err(emission) => colors[lane] = emission,
+            },
         }
+    }
+    if surfaces.iter().all(Option::is_none) {
+        return colors;
+    }
 
-        let color = total_diffuse * m.albedo[0]
-            + total_specular * m.albedo[1]
-            + reflection_color * m.reflectivity
-            + refraction_color * m.transparency;
+    let mut total_diffuse = [Vector3::zero(); LANES];
+    let mut total_specular = [Vector3::zero(); LANES];
+    for light in ctx.lights {
+        let mut shadow_origins = [Vector3::zero(); LANES];
+        let mut light_dirs = [Vector3::zero(); LANES];
+        let mut max_dist = [0.0; LANES];
+        for (lane, surface) in surfaces.iter().enumerate() {
+            if let Some(surface) = surface {
+                (shadow_origins[lane], light_dirs[lane], max_dist[lane]) = shadow_ray(surface, light);
+            }
+        }
+        let lit = surfaces.each_ref().map(Option::is_some);
+        let blockers = ctx.scene.first_hit_packet(&RayPacket::new(shadow_origins, light_dirs, lit), &max_dist);
+        let mut blocked = [false; LANES];
+        for (lane, blocker) in blockers.into_iter().enumerate() {
+            blocked[lane] = shadow_blocked_by(blocker, &shadow_origins[lane], &light_dirs[lane], max_dist[lane], ctx);
+        }
+        for (lane, surface) in surfaces.iter().enumerate() {
+            if let Some(surface) = surface {
+                let (diffuse, specular) = light_contribution(surface, light, light_dirs[lane], blocked[lane]);
+                total_diffuse[lane] = total_diffuse[lane] + diffuse;
+                total_specular[lane] = total_specular[lane] + specular;
+            }
+        }
+    }
 
-        color
-    } else {
-        skybox.sample(*ray_direction, texture_manager)
+    for (lane, surface) in surfaces.iter().enumerate() {
+        if let Some(surface) = surface {
+            colors[lane] = finish_surface(surface, &directions[lane], total_diffuse[lane], total_specular[lane], ctx, 0);
+        }
     }
+    colors
 }
 
 
@@ -310,57 +602,408 @@ fn procedural_sky(dir: Vector3) -> Vector3 {
     }
 }
 
-// pub fn render(framebuffer: &mut Framebuffer, objects: &[&dyn RayIntersect]) {
-pub fn render(
-    framebuffer: &mut Framebuffer,
-    objects: &[&dyn RayIntersect],
-    camera: &Camera,
-    lights: &[Light],
-    texture_manager: &TextureManager,
-    skybox: &Skybox,  // ← NUEVO
-) {
-    let width = framebuffer.width as f32;
-    let height = framebuffer.height as f32;
-    let aspect_ratio = width / height;
-    let fov = PI / 3.0;
-    let perspective_scale = (fov * 0.5).tan();
-
-    let colors: Vec<(i32, i32, Color)> = (0..framebuffer.height)
-        .into_par_iter()
-        .flat_map(|y| {
-            (0..framebuffer.width)
-                .map(|x| {
-                    let screen_x = (2.0 * x as f32) / width - 1.0;
-                    let screen_y = -(2.0 * y as f32) / height + 1.0;
-                    let screen_x = screen_x * aspect_ratio * perspective_scale;
-                    let screen_y = screen_y * perspective_scale;
-
-                    let ray_direction = Vector3::new(screen_x, screen_y, -1.0).normalized();
-                    let rotated_direction = camera.basis_change(&ray_direction);
-
-                    let ray_color = cast_ray(&camera.eye, &rotated_direction, objects, lights, 0, texture_manager, skybox);  // ← Pasa skybox
-
-                    let pixel_color = Color::new(
-                        (ray_color.x.clamp(0.0, 1.0) * 255.0) as u8,
-                        (ray_color.y.clamp(0.0, 1.0) * 255.0) as u8,
-                        (ray_color.z.clamp(0.0, 1.0) * 255.0) as u8,
-                        255,
-                    );
-
-                    (x, y, pixel_color)
+// Opciones del render que no dependen de la escena
+#[derive(Clone, Copy)]
+pub struct RenderOptions {
+    pub tiles: TileConfig,
+    // Con la cámara quieta se siguen sumando muestras hasta llegar a este número
+    pub max_samples: u32,
+    // Tiempo de render por cuadro que se intenta cumplir bajando la resolución mientras la
+    // cámara se mueve; None = siempre resolución completa
+    pub target_frame_time: Option<Duration>,
+    // Rayos primarios y sus sombras en paquetes de LANES píxeles vecinos
+    pub packets: bool,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            tiles: TileConfig::default(),
+            max_samples: 64,
+            target_frame_time: Some(Duration::from_millis(33)),
+            packets: true,
+        }
+    }
+}
+
+// Desplazamiento pseudoaleatorio dentro del píxel, en [0, 1)², para la muestra `sample`
+fn pixel_jitter(x: usize, y: usize, sample: u32) -> (f32, f32) {
+    let mut h = (x as u32).wrapping_mul(0x8da6_b343)
+        ^ (y as u32).wrapping_mul(0xd816_3841)
+        ^ sample.wrapping_mul(0xcb1a_b31f);
+    // Mezcla de enteros (lowbias32): cambia todos los bits aunque cambie uno solo
+    let mut mix = || {
+        h ^= h >> 16;
+        h = h.wrapping_mul(0x7feb_352d);
+        h ^= h >> 15;
+        h = h.wrapping_mul(0x846c_a68b);
+        h ^= h >> 16;
+        (h >> 8) as f32 / (1u32 << 24) as f32
+    };
+    (mix(), mix())
+}
+
+// Rayos primarios de la cámara para una imagen de `width` x `height` píxeles
+struct Viewport {
+    width: f32,
+    height: f32,
+    aspect_ratio: f32,
+    perspective_scale: f32,
+}
+
+impl Viewport {
+    fn new(framebuffer: &Framebuffer) -> Self {
+        let width = framebuffer.width as f32;
+        let height = framebuffer.height as f32;
+        let fov = PI / 3.0;
+        Viewport { width, height, aspect_ratio: width / height, perspective_scale: (fov * 0.5).tan() }
+    }
+
+    // Dirección del rayo que pasa por el punto (x, y) de la pantalla, en píxeles
+    fn direction(&self, camera: &Camera, x: f32, y: f32) -> Vector3 {
+        let screen_x = (2.0 * x) / self.width - 1.0;
+        let screen_y = -(2.0 * y) / self.height + 1.0;
+        let screen_x = screen_x * self.aspect_ratio * self.perspective_scale;
+        let screen_y = screen_y * self.perspective_scale;
+
+        let ray_direction = Vector3::new(screen_x, screen_y, -1.0).normalized();
+        camera.basis_change(&ray_direction)
+    }
+
+    // Colores (recortados a [0, 1]) de los rayos por los primeros `count` puntos, en
+    // paquete o rayo por rayo
+    fn trace(&self, camera: &Camera, ctx: &RenderContext, points: [(f32, f32); LANES], count: usize, packets: bool) -> [Vector3; LANES] {
+        let directions = points.map(|(x, y)| self.direction(camera, x, y));
+        let active = std::array::from_fn(|lane| lane < count);
+        let colors = if packets {
+            cast_packet(camera.eye, directions, active, ctx)
+        } else {
+            std::array::from_fn(|lane| {
+                if active[lane] { cast_ray(&camera.eye, &directions[lane], ctx, 0) } else { Vector3::zero() }
+            })
+        };
+        colors.map(|c| Vector3::new(c.x.clamp(0.0, 1.0), c.y.clamp(0.0, 1.0), c.z.clamp(0.0, 1.0)))
+    }
+}
+
+fn to_color(color: Vector3) -> Color {
+    Color::new((color.x * 255.0) as u8, (color.y * 255.0) as u8, (color.z * 255.0) as u8, 255)
+}
+
+// Renderiza un cuadro por tiles en paralelo y devuelve cuántos rayos primarios trazó.
+// Con `block` = 1 agrega una muestra por píxel a la acumulación del framebuffer: la
+// primera pasa por la esquina del píxel como siempre y las siguientes se desplazan al
+// azar dentro del píxel, así el promedio queda suavizado (antialiasing). Con la cámara
+// quieta y max_samples ya alcanzado no se traza nada.
+// Con `block` > 1 traza un rayo por el centro de cada bloque de `block` x `block`
+// píxeles y lo repite en todo el bloque (para moverse rápido con la cámara).
+pub fn render(framebuffer: &mut Framebuffer, camera: &Camera, ctx: &RenderContext, options: &RenderOptions, block: usize) -> usize {
+    let viewport = Viewport::new(framebuffer);
+    let (width, height) = (framebuffer.width as usize, framebuffer.height as usize);
+
+    if block > 1 {
+        let (columns, rows) = framebuffer.preview_size(block);
+        let half = block as f32 * 0.5;
+        // par_bridge reparte los tiles en el orden de la lista a medida que los hilos se liberan
+        tiles::split(framebuffer.preview_mut(block), columns, rows, options.tiles)
+            .into_iter()
+            .par_bridge()
+            .for_each(|mut tile| {
+                tile.for_each_run(LANES, |x, y, pixels| {
+                    // Centro del bloque, sin pasarse del borde en los bloques recortados
+                    let points = std::array::from_fn(|lane| {
+                        let center_x = (((x + lane) * block) as f32 + half).min(viewport.width - 0.5);
+                        let center_y = ((y * block) as f32 + half).min(viewport.height - 0.5);
+                        (center_x, center_y)
+                    });
+                    let colors = viewport.trace(camera, ctx, points, pixels.len(), options.packets);
+                    for (pixel, color) in pixels.iter_mut().zip(colors) {
+                        *pixel = to_color(color);
+                    }
                 })
-                .collect::<Vec<_>>()
-        })
-        .collect();
+            });
+        framebuffer.end_preview(block);
+        return columns * rows;
+    }
+
+    let sample = framebuffer.begin_sample(camera.revision);
+    if sample >= options.max_samples {
+        return 0;
+    }
+    tiles::split(framebuffer.accumulation_mut(), width, height, options.tiles)
+        .into_iter()
+        .par_bridge()
+        .for_each(|mut tile| {
+            tile.for_each_run(LANES, |x, y, sums| {
+                let points = std::array::from_fn(|lane| {
+                    let x = x + lane;
+                    let (jitter_x, jitter_y) = if sample == 0 { (0.0, 0.0) } else { pixel_jitter(x, y, sample) };
+                    (x as f32 + jitter_x, y as f32 + jitter_y)
+                });
+                // Cada muestra se recorta a [0, 1] como al mostrarla, para que un punto muy
+                // brillante no manche a los vecinos en el promedio
+                let colors = viewport.trace(camera, ctx, points, sums.len(), options.packets);
+                for (sum, color) in sums.iter_mut().zip(colors) {
+                    *sum = *sum + color;
+                }
+            })
+        });
+    framebuffer.end_sample();
+    width * height
+}
 
-    for (x, y, color) in colors {
-        framebuffer.set_current_color(color);
-        framebuffer.set_pixel(x, y);
+// Argumentos de línea de comandos:
+// [escena.json] [--materials archivo.json] [--pack carpeta]... [--export-obj x.obj] [--export-glb x.glb] [--bench]
+// [--samples n] [--target-ms n] [--tile-size n] [--tile-order scanline|spiral] [--no-packets]
+// [--frames n] [--frames-dir carpeta] [--fps n]
+struct Args {
+    scene_path: String,
+    materials_path: String,
+    packs: Vec<PathBuf>,
+    // Con alguno de estos se exporta la escena y se sale sin abrir la ventana
+    export_obj: Option<PathBuf>,
+    export_glb: Option<PathBuf>,
+    // Mide las consultas de intersección en lugar de abrir la ventana
+    bench: bool,
+    // Con --frames se renderiza una secuencia de imágenes (cubos y texturas animados) en
+    // frames_dir en lugar de abrir la ventana
+    frames: Option<usize>,
+    frames_dir: PathBuf,
+    fps: f32,
+    render: RenderOptions,
+}
+
+fn parse_args() -> Args {
+    let mut args = Args {
+        scene_path: "scenes/diorama.json".to_string(),
+        materials_path: materials::DEFAULT_LIBRARY.to_string(),
+        packs: Vec::new(),
+        export_obj: None,
+        export_glb: None,
+        bench: false,
+        frames: None,
+        frames_dir: PathBuf::from("frames"),
+        fps: 20.0,
+        render: RenderOptions::default(),
+    };
+    let mut it = std::env::args().skip(1);
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "--pack" => match it.next() {
+                Some(path) => args.packs.push(PathBuf::from(path)),
+                None => {
+                    eprintln!("--pack necesita una carpeta");
+                    std::process::exit(1);
+                }
+            },
+            "--materials" => match it.next() {
+                Some(path) => args.materials_path = path,
+                None => {
+                    eprintln!("--materials necesita un archivo");
+                    std::process::exit(1);
+                }
+            },
+            "--export-obj" | "--export-glb" => match it.next() {
+                Some(path) if arg == "--export-obj" => args.export_obj = Some(PathBuf::from(path)),
+                Some(path) => args.export_glb = Some(PathBuf::from(path)),
+                None => {
+                    eprintln!("{} necesita un archivo de salida", arg);
+                    std::process::exit(1);
+                }
+            },
+            "--bench" => args.bench = true,
+            "--frames" => match it.next().and_then(|n| n.parse().ok()) {
+                Some(frames) if frames > 0 => args.frames = Some(frames),
+                _ => {
+                    eprintln!("--frames necesita un número mayor que 0");
+                    std::process::exit(1);
+                }
+            },
+            "--frames-dir" => match it.next() {
+                Some(path) => args.frames_dir = PathBuf::from(path),
+                None => {
+                    eprintln!("--frames-dir necesita una carpeta");
+                    std::process::exit(1);
+                }
+            },
+            "--fps" => match it.next().and_then(|n| n.parse::<f32>().ok()) {
+                Some(fps) if fps > 0.0 => args.fps = fps,
+                _ => {
+                    eprintln!("--fps necesita un número mayor que 0");
+                    std::process::exit(1);
+                }
+            },
+            "--no-packets" => args.render.packets = false,
+            "--tile-size" => match it.next().and_then(|n| n.parse().ok()) {
+                Some(size) if size > 0 => args.render.tiles.size = size,
+                _ => {
+                    eprintln!("--tile-size necesita un número de píxeles mayor que 0");
+                    std::process::exit(1);
+                }
+            },
+            "--samples" => match it.next().and_then(|n| n.parse().ok()) {
+                Some(samples) if samples > 0 => args.render.max_samples = samples,
+                _ => {
+                    eprintln!("--samples necesita un número mayor que 0");
+                    std::process::exit(1);
+                }
+            },
+            "--target-ms" => match it.next().and_then(|n| n.parse::<u64>().ok()) {
+                // 0 apaga la resolución dinámica
+                Some(0) => args.render.target_frame_time = None,
+                Some(ms) => args.render.target_frame_time = Some(Duration::from_millis(ms)),
+                None => {
+                    eprintln!("--target-ms necesita un número de milisegundos");
+                    std::process::exit(1);
+                }
+            },
+            "--tile-order" => match it.next().as_deref().and_then(TileOrder::parse) {
+                Some(order) => args.render.tiles.order = order,
+                None => {
+                    eprintln!("--tile-order necesita \"scanline\" o \"spiral\"");
+                    std::process::exit(1);
+                }
+            },
+            _ => args.scene_path = arg,
+        }
+    }
+    args
+}
+
+// Carga la biblioteca de materiales y la escena (sin texturas)
+fn load_scene_files(args: &Args) -> Result<(MaterialLibrary, LoadedScene), String> {
+    // Con --pack las texturas salen del resource pack
+    let mut mat = MaterialLibrary::load(Path::new(&args.materials_path))
+        .map_err(|e| format!("Error cargando los materiales: {}", e))?;
+    if !args.packs.is_empty() {
+        let pack = ResourcePack::open(&args.packs)
+            .map_err(|e| format!("Error abriendo el resource pack: {}", e))?;
+        for warning in pack.apply(&mut mat) {
+            eprintln!("Aviso: {}", warning);
+        }
+    }
+
+    // Carga la escena desde archivo (por defecto scenes/diorama.json)
+    let loaded = scene_file::load_scene(Path::new(&args.scene_path), &mut mat)
+        .map_err(|e| format!("Error cargando la escena: {}", e))?;
+    for warning in &loaded.warnings {
+        eprintln!("Aviso: {}", warning);
+    }
+    if loaded.merge.blocks > 0 {
+        println!("Bloques juntados: {}", loaded.merge);
+    }
+    Ok((mat, loaded))
+}
+
+// Carga la biblioteca de materiales, la escena y sus texturas. Devuelve también
+// los archivos y carpetas que hay que vigilar para recargarla.
+fn load_world(
+    args: &Args,
+    window: &mut RaylibHandle,
+    thread: &RaylibThread,
+    texture_manager: &mut TextureManager,
+) -> Result<(MaterialLibrary, LoadedScene, Vec<PathBuf>), String> {
+    let (mat, loaded) = load_scene_files(args)?;
+
+    // Carga las texturas de los materiales y del skybox (solo las nuevas o modificadas)
+    texture_manager.load_all(window, thread, &mat.textures)?;
+
+    let mut watched = vec![
+        PathBuf::from(&args.scene_path),
+        PathBuf::from(&args.materials_path),
+        PathBuf::from("assets"),
+    ];
+    watched.extend(loaded.sources.iter().cloned());
+    // Texturas de fuera de assets/ (p. ej. de un resource pack) y su .mcmeta, si lo tienen
+    for (_, texture) in mat.textures.iter().filter(|(_, t)| !t.starts_with("assets/")) {
+        watched.push(PathBuf::from(texture));
+        watched.push(PathBuf::from(format!("{}.mcmeta", texture)));
+    }
+    Ok((mat, loaded, watched))
+}
+
+// Exporta la escena a OBJ y/o glTF según los argumentos
+fn export(args: &Args) -> Result<(), String> {
+    let (mat, loaded) = load_scene_files(args)?;
+    if let Some(path) = &args.export_obj {
+        export::export_obj(&loaded.scene, &mat, path).map_err(|e| format!("Error exportando {}: {}", path.display(), e))?;
+        println!("Escena exportada a {}", path.display());
+    }
+    if let Some(path) = &args.export_glb {
+        export::export_glb(&loaded.scene, &mat, &loaded.lights, path)
+            .map_err(|e| format!("Error exportando {}: {}", path.display(), e))?;
+        println!("Escena exportada a {}", path.display());
+    }
+    Ok(())
+}
+
+// Renderiza `frames` cuadros seguidos, cada uno con todas sus muestras, a frames_dir/frame_0000.png...
+fn render_sequence(args: &Args, frames: usize) -> Result<(), String> {
+    let (mat, loaded) = load_scene_files(args)?;
+    let mut texture_manager = TextureManager::new();
+    texture_manager
+        .load_cpu(&mat.textures)
+        .map_err(|e| format!("Error cargando las texturas: {}", e))?;
+    std::fs::create_dir_all(&args.frames_dir)
+        .map_err(|e| format!("Error creando {}: {}", args.frames_dir.display(), e))?;
+
+    let LoadedScene { mut scene, lights, camera, skybox, spins, .. } = loaded;
+    let mut framebuffer = Framebuffer::new(900, 700, Color::BLACK);
+    for frame in 0..frames {
+        let time = frame as f32 / args.fps;
+        scene.animate(&spins, time);
+        let ctx = RenderContext {
+            scene: &scene,
+            lights: &lights,
+            materials: &mat,
+            texture_manager: &texture_manager,
+            skybox: &skybox,
+            time,
+        };
+        framebuffer.reset_accumulation();
+        while render(&mut framebuffer, &camera, &ctx, &args.render, 1) > 0 {}
+        let path = args.frames_dir.join(format!("frame_{:04}.png", frame));
+        if !framebuffer.render_to_file(&path.to_string_lossy()) {
+            return Err(format!("Error guardando {}", path.display()));
+        }
+        println!("{}", path.display());
     }
+    Ok(())
 }
 
 // Y en la función main:
 fn main() {
+    let args = parse_args();
+    if args.export_obj.is_some() || args.export_glb.is_some() {
+        if let Err(e) = export(&args) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Some(frames) = args.frames {
+        if let Err(e) = render_sequence(&args, frames) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if args.bench {
+        match load_scene_files(&args) {
+            Ok((mat, loaded)) => {
+                bench::run_bvh(&loaded.scene, &loaded.camera, &loaded.lights);
+                bench::run_packets(&loaded.scene, &loaded.camera, &loaded.lights);
+                bench::run_materials(&loaded, &mat);
+                bench::run_render(&loaded, &mat, &args.render);
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
     let window_width = 900;
     let window_height = 700;
 
@@ -373,199 +1016,31 @@ fn main() {
     let mut framebuffer = Framebuffer::new(window_width as i32, window_height as i32, Color::BLACK);
     framebuffer.set_background_color(Color::new(201, 201, 201, 255));
 
-    // Carga todas las texturas
+    // Carga materiales, escena y texturas; si algo falla al iniciar no hay nada que mostrar
     let mut texture_manager = TextureManager::new();
-    let textures = [
-        "assets/brick.png", "assets/sand.png", "assets/water_flow.png",
-        "assets/grass_path_side.png", "assets/grass_path_top.png", "assets/dirt.png",
-        "assets/iron_ore.png", "assets/planks_big_oak.png", "assets/stone_granite.png",
-        "assets/diamond_ore.png", "assets/stone_diorite.png", "assets/stone.png",
-        "assets/azalea_leaves_flowers.png", "assets/bookshelf.png", "assets/planks_oak.png",
-        "assets/furnace_top.png", "assets/furnace_front_off.png", "assets/pumpkin_top.png",
-        "assets/pumpkin_side.png", "assets/pumpkin_face_off.png", "assets/noteblock.png",
-        "assets/redstone_lamp_on.png", "assets/cielo_top.png", "assets/cielo_bot.png",
-        "assets/cielo1.png", "assets/cielo2.png"
-    ];
-    for texture in &textures {
-        texture_manager.load_texture(&mut window, &raylib_thread, texture);
-    }
-
-    let skybox = Skybox::new_simple_minecraft();
-
-    // Crea los materiales
-    let mat = Materials::new();
-
-    // Construye la escena
-    let mut scene = Scene::new();
-    
-    // ARENA
-    scene.add_rectangle(Vector3::new(6.0, -6.0, 3.0), Vector3::new(3.0, 1.0, 6.0), mat.sand.clone());
-    scene.add_rectangle(Vector3::new(8.0, -4.0, 6.0), Vector3::new(1.0, 1.0, 1.0), mat.sand.clone());
-    scene.add_rectangle(Vector3::new(3.0, -4.0, 8.0), Vector3::new(4.0, 1.0, 1.0), mat.sand.clone());
-    scene.add_rectangle(Vector3::new(8.0, -4.0, -3.0), Vector3::new(1.0, 1.0, 2.0), mat.sand.clone());
-    scene.add_rectangle(Vector3::new(2.0, -4.0, 5.0), Vector3::new(1.0, 1.0, 2.0), mat.sand.clone());
-    scene.add_rectangle(Vector3::new(6.0, -4.0, -1.0), Vector3::new(1.0, 1.0, 2.0), mat.sand.clone());
-    scene.add_rectangle(Vector3::new(4.0, -4.0, 2.0), Vector3::new(1.0, 1.0, 1.0), mat.sand.clone());
-    scene.add_rectangle(Vector3::new(0.0, -2.0, 4.0), Vector3::new(1.0, 1.0, 3.0), mat.sand.clone());
-    scene.add_rectangle(Vector3::new(4.0, -2.0, 0.0), Vector3::new(1.0, 1.0, 1.0), mat.sand.clone());
-    scene.add_rectangle(Vector3::new(2.0, -2.0, 2.0), Vector3::new(1.0, 1.0, 1.0), mat.sand.clone());
-
-    // AGUA
-    scene.add_rectangle(Vector3::new(8.0, -4.0, 2.0), Vector3::new(1.0, 1.0, 3.0), mat.water.clone());
-    scene.add_rectangle(Vector3::new(6.0, -4.0, 4.0), Vector3::new(1.0, 1.0, 3.0), mat.water.clone());
-    scene.add_rectangle(Vector3::new(4.0, -4.0, 5.0), Vector3::new(1.0, 1.0, 2.0), mat.water.clone());
-
-    // TIERRA CON GRASS
-    scene.add_rectangle(Vector3::new(8.0, -4.0, -6.0), Vector3::new(1.0, 1.0, 1.0), mat.dirt_grass.clone());
-    scene.add_rectangle(Vector3::new(6.0, -4.0, -6.0), Vector3::new(1.0, 1.0, 1.0), mat.dirt_grass.clone());
-    scene.add_rectangle(Vector3::new(6.0, -4.0, -8.0), Vector3::new(1.0, 1.0, 1.0), mat.dirt_grass.clone());
-    scene.add_rectangle(Vector3::new(-4.0, -2.0, -6.0), Vector3::new(5.0, 1.0, 3.0), mat.dirt_grass.clone());
-    scene.add_rectangle(Vector3::new(0.0, -2.0, -1.0), Vector3::new(3.0, 1.0, 2.0), mat.dirt_grass.clone());
-    scene.add_rectangle(Vector3::new(4.0, -2.0, -3.0), Vector3::new(1.0, 1.0, 2.0), mat.dirt_grass.clone());
-    scene.add_rectangle(Vector3::new(2.0, -2.0, -5.0), Vector3::new(1.0, 1.0, 2.0), mat.dirt_grass.clone());
-
-    // TIERRA
-    scene.add_rectangle(Vector3::new(-3.0, -6.0, 6.0), Vector3::new(6.0, 1.0, 3.0), mat.dirt.clone());
-    scene.add_rectangle(Vector3::new(-2.0, -6.0, -2.0), Vector3::new(5.0, 1.0, 5.0), mat.dirt.clone());
-    scene.add_rectangle(Vector3::new(-8.0, -4.0, -1.0), Vector3::new(1.0, 1.0, 4.0), mat.dirt.clone());
-
-    // MADERA OSCURA
-    scene.add_rectangle(Vector3::new(-5.0, -4.0, 6.0), Vector3::new(4.0, 1.0, 3.0), mat.madera_oscura.clone());
-    scene.add_rectangle(Vector3::new(-7.0, -2.0, 0.0), Vector3::new(2.0, 1.0, 3.0), mat.madera_oscura.clone());
-    scene.add_rectangle(Vector3::new(-5.0, 5.5, 1.0), Vector3::new(4.0, 0.5, 6.0), mat.madera_oscura.clone());
-    scene.add_rectangle(Vector3::new(-5.0, 6.0, 1.0), Vector3::new(2.0, 0.5, 4.0), mat.madera_oscura.clone());
-
-
-
-
-    // HIERRO
-    scene.add_rectangle(Vector3::new(2.0, -6.0, -8.0), Vector3::new(1.0, 1.0, 1.0), mat.hierro.clone());
-    scene.add_rectangle(Vector3::new(-8.0, -6.0, 2.0), Vector3::new(1.0, 1.0, 1.0), mat.hierro.clone());
-    scene.add_rectangle(Vector3::new(-7.0, -6.0, -8.0), Vector3::new(2.0, 1.0, 1.0), mat.hierro.clone());
-
-    // GRANITO
-    scene.add_rectangle(Vector3::new(-3.0, -6.0, -8.0), Vector3::new(2.0, 1.0, 1.0), mat.granito.clone());
-    scene.add_rectangle(Vector3::new(-5.0, -4.0, -8.0), Vector3::new(2.0, 1.0, 1.0), mat.granito.clone());
-    scene.add_rectangle(Vector3::new(-8.0, -6.0, -5.0), Vector3::new(1.0, 1.0, 2.0), mat.granito.clone());
-
-    // DIAMANTE
-    scene.add_rectangle(Vector3::new(0.0, -6.0, -8.0), Vector3::new(1.0, 1.0, 1.0), mat.diamante.clone());
-    scene.add_rectangle(Vector3::new(-8.0, -6.0, 0.0), Vector3::new(1.0, 1.0, 1.0), mat.diamante.clone());
-
-    // ROCA
-    scene.add_rectangle(Vector3::new(-8.0, -6.0, -2.0), Vector3::new(1.0, 1.0, 1.0), mat.roca.clone());
-    scene.add_rectangle(Vector3::new(4.0, -6.0, -8.0), Vector3::new(1.0, 1.0, 1.0), mat.roca.clone());
-    scene.add_rectangle(Vector3::new(-8.0, -4.0, -7.0), Vector3::new(1.0, 1.0, 2.0), mat.roca.clone());
-    scene.add_rectangle(Vector3::new(-1.0, -4.0, -8.0), Vector3::new(2.0, 1.0, 1.0), mat.roca.clone());
-
-    // FLORES
-    scene.add_rectangle(Vector3::new(-1.0, -2.0, 8.0), Vector3::new(2.0, 1.0, 1.0), mat.flores.clone());
-    scene.add_rectangle(Vector3::new(-2.0, 0.0, 8.0), Vector3::new(1.0, 1.0, 1.0), mat.flores.clone());
-    scene.add_rectangle(Vector3::new(-2.0, 0.0, -2.0), Vector3::new(1.0, 1.0, 1.0), mat.flores.clone());
-    scene.add_rectangle(Vector3::new(-4.0, 0.0, -4.0), Vector3::new(1.0, 1.0, 1.0), mat.flores.clone());
-
-    // LIBRERÍA
-    scene.add_rectangle(Vector3::new(-4.0, -2.0, 6.0), Vector3::new(1.0, 1.0, 3.0), mat.libreria.clone());
-    scene.add_rectangle(Vector3::new(-4.0, 0.0, 2.0), Vector3::new(1.0, 1.0, 1.0), mat.libreria.clone());
-    scene.add_rectangle(Vector3::new(-4.0, 2.0, 4.0), Vector3::new(1.0, 1.0, 1.0), mat.libreria.clone());
-    scene.add_rectangle(Vector3::new(-2.0, 0.0, 6.0), Vector3::new(1.0, 1.0, 1.0), mat.libreria.clone());
-    
-
-
-    // MADERA
-    scene.add_rectangle(Vector3::new(-4.0, 2.0, -1.0), Vector3::new(1.0, 3.0, 2.0), mat.madera.clone());
-    scene.add_rectangle(Vector3::new(-4.0, 1.0, 8.0), Vector3::new(1.0, 2.0, 1.0), mat.madera.clone());
-    scene.add_rectangle(Vector3::new(-4.0, 3.0, 6.0), Vector3::new(1.0, 2.0, 1.0), mat.madera.clone());
-    scene.add_rectangle(Vector3::new(-4.0, 4.0, 3.0), Vector3::new(1.0, 1.0, 2.0), mat.madera.clone());
-    scene.add_rectangle(Vector3::new(-6.0, 2.0, -4.0), Vector3::new(1.0, 3.0, 1.0), mat.madera.clone());
-    scene.add_rectangle(Vector3::new(-8.0, 4.0, -4.0), Vector3::new(1.0, 1.0, 1.0), mat.madera.clone());
-
-    // HORNO
-    scene.add_rectangle(Vector3::new(-4.0, 2.0, 2.0), Vector3::new(1.0, 1.0, 1.0), mat.horno.clone());
-    scene.add_rectangle(Vector3::new(-4.0, 0.0, 5.0), Vector3::new(1.0, 1.0, 2.0), mat.horno.clone());
-    scene.add_rectangle(Vector3::new(-2.0, 0.0, 4.0), Vector3::new(1.0, 1.0, 1.0), mat.horno.clone());
-
-    //musical
-    scene.add_rectangle(Vector3::new(-2.0, 0.0, 2.0), Vector3::new(1.0, 1.0, 1.0), mat.musical.clone());
-
-    //calabaza
-    scene.add_rectangle(Vector3::new(2.0, -2.0, -8.0), Vector3::new(1.0, 1.0, 1.0), mat.calabaza.clone());
-    scene.add_rectangle(Vector3::new(6.0, -2.0, -4.0), Vector3::new(1.0, 1.0, 1.0), mat.calabaza.clone());
-
-    //Redstone
-    scene.add_rectangle(Vector3::new(4.0, -2.0, -6.0), Vector3::new(1.0, 1.0, 1.0), mat.redstone_lamp.clone());
-    scene.add_rectangle(Vector3::new(-6.0, 0.0, -2.0), Vector3::new(1.0, 1.0, 1.0), mat.redstone_lamp.clone());
-
-    // Después de construir la escena, antes del loop principal:
-
-    let objects_slice = scene.as_slice();
-
-    // ¡CREA MÚLTIPLES LUCES!
-    let lights = vec![
-        // Luz principal - Sol alto en el cielo
-        Light {
-            position: Vector3::new(10.0, 15.0, 5.0),
-            color: Vector3::new(1.0, 0.95, 0.9),  // Luz solar cálida
-            intensity: 1.2,
-        },
-
-        Light {
-            position: Vector3::new(0.0, 0.0, 14.0),
-            color: Vector3::new(1.0, 0.95, 0.9),  // Luz solar cálida
-            intensity: 1.2,
-        },
-
-        Light {
-            position: Vector3::new(0.0, -10.0, 0.0),
-            color: Vector3::new(1.0, 0.95, 0.9),  // Luz solar cálida
-            intensity: 1.2,
-        },
-
-        Light {
-            position: Vector3::new(0.0, 0.0, -14.0),
-            color: Vector3::new(1.0, 0.95, 0.9),  // Luz solar cálida
-            intensity: 1.2,
-        },
-
-        Light {
-            position: Vector3::new(-6.0, 0.0, 0.0),
-            color: Vector3::new(1.0, 0.95, 0.9),  // Luz solar cálida
-            intensity: 1.2,
-        },
-        // Luz de relleno - Más suave, desde otro ángulo
-        Light {
-            position: Vector3::new(-8.0, 10.0, -5.0),
-            color: Vector3::new(0.7, 0.8, 1.0),  // Luz fría azulada
-            intensity: 0.5,
-        },
-        // Luz ambiental baja - Simula luz rebotada
-        Light {
-            position: Vector3::new(0.0, 5.0, 10.0),
-            color: Vector3::new(1.0, 1.0, 1.0),  // Blanca neutral
-            intensity: 0.3,
-        },
-        // Luz de acento - Para resaltar zonas específicas
-        Light {
-            position: Vector3::new(4.0, -2.0, -6.0),
-            color: Vector3::new(1.0, 0.8, 0.6),  // Cálida anaranjada
-            intensity: 0.6,
-        },
-
-        
-    ];
+    let (mut mat, loaded, mut watched) = match load_world(&args, &mut window, &raylib_thread, &mut texture_manager) {
+        Ok(world) => world,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let LoadedScene { mut scene, mut lights, mut camera, mut skybox, mut spins, .. } = loaded;
+
+    // Recarga en caliente: se revisan los archivos dos veces por segundo
+    let mut watcher = FileWatcher::new(watched.clone(), Duration::from_millis(500));
+    let mut reload_error: Option<String> = None;
 
-    let mut camera = Camera::new(
-        Vector3::new(30.0, 5.0, 30.0),
-        Vector3::new(0.0, 0.0, 0.0),
-        Vector3::new(0.0, 1.0, 0.0),
-    );
+    let mut resolution = args.render.target_frame_time.map(ResolutionController::new);
+    let mut last_revision = camera.revision;
+    let mut last_time = 0.0;
 
     let rotation_speed = PI / 50.0;
     let zoom_speed = 2.0;
 
     while !window.window_should_close() {
-        framebuffer.clear();
-
+        // No se limpia el framebuffer: render() reescribe todos los píxeles con el promedio
+        // de las muestras, y con la imagen ya convergida se sigue mostrando la misma
         if window.is_key_down(KeyboardKey::KEY_A) {
             camera.orbit(rotation_speed, 0.0);
         }
@@ -585,8 +1060,62 @@ fn main() {
         if window.is_key_down(KeyboardKey::KEY_UP) {
             camera.zoom(-zoom_speed);  // Positivo = alejar
         }
+        // Si cambió algún archivo se reconstruye todo menos la cámara
+        if watcher.poll() {
+            match load_world(&args, &mut window, &raylib_thread, &mut texture_manager) {
+                Ok((materials, loaded, paths)) => {
+                    mat = materials;
+                    scene = loaded.scene;
+                    lights = loaded.lights;
+                    skybox = loaded.skybox;
+                    spins = loaded.spins;
+                    framebuffer.reset_accumulation();
+                    if paths != watched {
+                        watched = paths;
+                        watcher.set_roots(watched.clone());
+                    }
+                    reload_error = None;
+                    println!("Escena recargada");
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    reload_error = Some(e);
+                }
+            }
+        }
+
+        // Los cubos animados se giran y se reajusta su BVH en cada cuadro; las texturas animadas
+        // solo cambian cuando pasan de cuadro. Recién ahí se descartan las muestras acumuladas
+        let time = window.get_time() as f32;
+        if !spins.is_empty() {
+            scene.animate(&spins, time);
+        }
+        if !spins.is_empty() || texture_manager.animation_changed(last_time, time) {
+            framebuffer.reset_accumulation();
+        }
+        last_time = time;
+
         // ¡Pasa el vector de luces en lugar de una sola luz!
-        render(&mut framebuffer, &objects_slice, &camera, &lights, &texture_manager, &skybox);
-        framebuffer.swap_buffers(&mut window, &raylib_thread);
+        let ctx = RenderContext {
+            scene: &scene,
+            lights: &lights,
+            materials: &mat,
+            texture_manager: &texture_manager,
+            skybox: &skybox,
+            time,
+        };
+        // Mientras la cámara se mueve se baja la resolución para llegar al tiempo pedido
+        let moving = camera.revision != last_revision;
+        last_revision = camera.revision;
+        let block = match &resolution {
+            Some(controller) if moving => controller.block(framebuffer.width as usize, framebuffer.height as usize),
+            _ => 1,
+        };
+        let start = Instant::now();
+        let rays = render(&mut framebuffer, &camera, &ctx, &args.render, block);
+        if let Some(controller) = &mut resolution {
+            controller.record(rays, start.elapsed());
+        }
+        framebuffer.swap_buffers(&mut window, &raylib_thread, reload_error.as_deref());
     }
 }
\ No newline at end of file
@@ -1,6 +1,15 @@
 use raylib::prelude::*;
 
-#[derive(Clone)]
+// Índice de un material en la MaterialLibrary: los cubos y los HitInfo guardan
+// esto en lugar de una copia del material
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct MaterialId(pub u32);
+
+// Índice de una textura en el TextureSet de la biblioteca (y en el TextureManager)
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct TextureId(pub u32);
+
+#[derive(Clone, Copy, PartialEq)]
 pub struct Material {
     pub diffuse: Color,
     pub specular: f32,
@@ -8,15 +17,63 @@ pub struct Material {
     pub transparency: f32,
     pub refractive_index: f32,
     pub albedo: [f32; 2],
-    pub texture_path:  Option<TextureFaces>,
-    pub emission: Vector3,  
+    pub textures: Option<TextureFaces>,
+    pub emission: Vector3,
     pub emission_strength: f32,
+    // Absorción por unidad de distancia de cada canal (Beer-Lambert) para los rayos que
+    // atraviesan un material transparente
+    pub absorption: Vector3,
+    // Los texels con alfa < 0.5 de la textura no existen: el rayo (y la sombra) sigue de largo
+    pub alpha_test: bool,
+    // Si está, se sombrea con GGX en lugar de Phong (specular y albedo no se usan)
+    pub pbr: Option<Pbr>,
 }
 
-#[derive(Clone)]
-pub struct TextureFaces {
-    pub top: String,
-    pub bottom: String,
-    pub side_x: String,
-    pub side_z: String,
+// Parámetros metal/rugosidad. El color base es `diffuse` (o la textura)
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Pbr {
+    pub metallic: f32,
+    pub roughness: f32,
+    // Reflectancia de frente (F0) de la parte no metálica
+    pub f0: f32,
+}
+
+impl Pbr {
+    // F0 de un dieléctrico con ese índice de refracción (1.5 -> 0.04)
+    pub fn f0_from_ior(ior: f32) -> f32 {
+        ((ior - 1.0) / (ior + 1.0)).powi(2)
+    }
+}
+
+// Textura de cada cara: ids en los materiales, rutas mientras se leen archivos
+#[derive(Clone, Copy, PartialEq)]
+pub struct TextureFaces<T = TextureId> {
+    pub top: T,
+    pub bottom: T,
+    pub side_x: T,
+    pub side_z: T,
+}
+
+impl<T> TextureFaces<T> {
+    // Textura de la cara con esa normal (en espacio local del cubo)
+    pub fn face(&self, normal: Vector3) -> &T {
+        if normal.y > 0.9 {
+            &self.top
+        } else if normal.y < -0.9 {
+            &self.bottom
+        } else if normal.x.abs() > 0.9 {
+            &self.side_x
+        } else {
+            &self.side_z
+        }
+    }
+
+    pub fn map<U>(&self, mut f: impl FnMut(&T) -> U) -> TextureFaces<U> {
+        TextureFaces {
+            top: f(&self.top),
+            bottom: f(&self.bottom),
+            side_x: f(&self.side_x),
+            side_z: f(&self.side_z),
+        }
+    }
 }
@@ -1,337 +1,323 @@
+// materials.rs
+// Biblioteca de materiales indexada por nombre y cargada desde JSON
+// (por defecto materials/default.json). Agregar un bloque nuevo es
+// agregar una entrada al archivo, sin tocar el código.
 use raylib::prelude::*;
-use crate::material::{Material, TextureFaces};
+use serde::Deserialize;
+use serde_json::value::RawValue;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
 
-pub struct Materials {
-    pub horno: Material,
-    pub madera: Material,
-    pub libreria: Material,
-    pub flores: Material,
-    pub diamante: Material,
-    pub diorita: Material,
-    pub roca: Material,
-    pub granito: Material,
-    pub dirt_grass: Material,
-    pub brick: Material,
-    pub sand: Material,
-    pub water: Material,
-    pub dirt: Material,
-    pub hierro: Material,
-    pub madera_oscura: Material,
-    pub redstone_lamp: Material,
-    pub musical: Material,
-    pub calabaza: Material,
+use crate::import;
+use crate::material::{Material, MaterialId, Pbr, TextureFaces};
+use crate::scene_file::{json_message, line_of};
+use crate::textures::TextureSet;
+
+pub const DEFAULT_LIBRARY: &str = "materials/default.json";
+
+// También es el arreglo de materiales de la escena: los cubos guardan un MaterialId
+// y las texturas se guardan una sola vez en `textures`
+pub struct MaterialLibrary {
+    materials: Vec<Material>,
+    // Vacío para los materiales sin nombre (p. ej. los colores de un .vox)
+    names: Vec<String>,
+    index: HashMap<String, MaterialId>,
+    // Bloque de Minecraft que representa cada material (para resource packs e importadores)
+    blocks: Vec<Option<String>>,
+    by_block: HashMap<String, MaterialId>,
+    pub textures: TextureSet,
+}
+
+// Error de carga con archivo, línea y el material que lo provocó
+#[derive(Debug)]
+pub struct LibraryError {
+    pub path: PathBuf,
+    pub line: usize,
+    pub material: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for LibraryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: ", self.path.display(), self.line)?;
+        if let Some(material) = &self.material {
+            write!(f, "material \"{}\": ", material)?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for LibraryError {}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MaterialEntry {
+    #[serde(default)]
+    block: Option<String>,
+    // [r, g, b] o [r, g, b, a] en 0..=255
+    diffuse: Vec<u8>,
+    specular: f32,
+    #[serde(default)]
+    reflectivity: f32,
+    #[serde(default)]
+    transparency: f32,
+    #[serde(default = "default_refractive_index")]
+    refractive_index: f32,
+    albedo: [f32; 2],
+    #[serde(default)]
+    emission: [f32; 3],
+    #[serde(default)]
+    emission_strength: f32,
+    #[serde(default)]
+    absorption: [f32; 3],
+    #[serde(default)]
+    alpha_test: bool,
+    #[serde(default)]
+    textures: Option<TexturesEntry>,
+    #[serde(default)]
+    pbr: Option<PbrEntry>,
 }
 
-impl Materials {
+// Parámetros físicos opcionales; sin `specular` el F0 sale de `refractive_index`
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PbrEntry {
+    #[serde(default)]
+    metallic: f32,
+    roughness: f32,
+    #[serde(default)]
+    specular: Option<f32>,
+}
+
+// Una sola textura para las 4 caras o una por cara
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TexturesEntry {
+    All(String),
+    Faces {
+        top: String,
+        bottom: String,
+        side_x: String,
+        side_z: String,
+    },
+}
+
+fn default_refractive_index() -> f32 {
+    1.0
+}
+
+impl MaterialLibrary {
     pub fn new() -> Self {
-        Materials {
-            horno: Material {
-                diffuse: Color::new(130, 130, 130, 255),
-                specular: 80.0,
-                reflectivity: 0.2,
-                transparency: 0.0,
-                refractive_index: 1.0,
-                albedo: [0.7, 0.3],
-                emission: Vector3::zero(),
-                emission_strength: 0.0,
-                texture_path: Some(TextureFaces {
-                    top: "assets/furnace_top.png".to_string(),
-                    bottom: "assets/furnace_front_off.png".to_string(),
-                    side_x: "assets/furnace_front_off.png".to_string(),
-                    side_z: "assets/furnace_front_off.png".to_string(),
-                }),
-            },
+        MaterialLibrary {
+            materials: Vec::new(),
+            names: Vec::new(),
+            index: HashMap::new(),
+            blocks: Vec::new(),
+            by_block: HashMap::new(),
+            textures: TextureSet::default(),
+        }
+    }
 
-            // LÁMPARA DE REDSTONE - ¡EMISIVA!
-            redstone_lamp: Material {
-                diffuse: Color::new(255, 200, 150, 255),
-                specular: 150.0,
-                reflectivity: 0.4,
-                transparency: 0.0,
-                refractive_index: 1.0,
-                albedo: [1.0, 0.5],
-                emission: Vector3::new(1.0, 0.8, 0.5),  // Luz cálida naranja
-                emission_strength: 4.0,  // Muy brillante
-                texture_path: Some(TextureFaces {
-                    top: "assets/redstone_lamp_on.png".to_string(),
-                    bottom: "assets/redstone_lamp_on.png".to_string(),
-                    side_x: "assets/redstone_lamp_on.png".to_string(),
-                    side_z: "assets/redstone_lamp_on.png".to_string(),
-                }),
-            },
+    pub fn load(path: &Path) -> Result<Self, LibraryError> {
+        let source = std::fs::read_to_string(path).map_err(|e| LibraryError {
+            path: path.to_path_buf(),
+            line: 0,
+            material: None,
+            message: e.to_string(),
+        })?;
+        Self::parse(path, &source)
+    }
 
-            musical: Material {
-                diffuse: Color::new(139, 90, 43, 255),
-                specular: 20.0,
-                reflectivity: 0.05,
-                transparency: 0.0,
-                refractive_index: 1.0,
-                albedo: [0.85, 0.15],
-                emission: Vector3::zero(),
-                emission_strength: 0.0,
-                texture_path: Some(TextureFaces {
-                    top: "assets/noteblock.png".to_string(),
-                    bottom: "assets/noteblock.png".to_string(),
-                    side_x: "assets/noteblock.png".to_string(),
-                    side_z: "assets/noteblock.png".to_string(),
-                }),
-            },
+    pub fn parse(path: &Path, source: &str) -> Result<Self, LibraryError> {
+        let raw: BTreeMap<String, &RawValue> = serde_json::from_str(source).map_err(|e| LibraryError {
+            path: path.to_path_buf(),
+            line: e.line(),
+            material: None,
+            message: json_message(&e),
+        })?;
 
-            // CALABAZA - Puede ser emisiva si quieres Jack-o'-lantern
-            calabaza: Material {
-                diffuse: Color::new(200, 120, 40, 255),
-                specular: 5.0,
-                reflectivity: 0.0,
-                transparency: 0.0,
-                refractive_index: 1.0,
-                albedo: [0.95, 0.05],
-                emission: Vector3::new(1.0, 0.6, 0.2),  // Luz de calabaza
-                emission_strength: 1.5,  // Suave resplandor
-                texture_path: Some(TextureFaces {
-                    top: "assets/pumpkin_top.png".to_string(),
-                    bottom: "assets/pumpkin_top.png".to_string(),
-                    side_x: "assets/pumpkin_face_off.png".to_string(),
-                    side_z: "assets/pumpkin_side.png".to_string(),
-                }),
-            },
+        let mut library = MaterialLibrary::new();
+        for (name, entry) in raw {
+            let error = |line: usize, message: String| LibraryError {
+                path: path.to_path_buf(),
+                line: line_of(source, entry.get()) + line.saturating_sub(1),
+                material: Some(name.clone()),
+                message,
+            };
+            let parsed: MaterialEntry =
+                serde_json::from_str(entry.get()).map_err(|e| error(e.line(), json_message(&e)))?;
+            let material = parsed.validate(&mut library.textures).map_err(|message| error(1, message))?;
+            library.insert(&name, material, parsed.block);
+        }
+        Ok(library)
+    }
 
-            madera: Material {
-                diffuse: Color::new(162, 130, 78, 255),
-                specular: 15.0,
-                reflectivity: 0.05,
-                transparency: 0.0,
-                refractive_index: 1.0,
-                albedo: [0.85, 0.15],
-                emission: Vector3::zero(),
-                emission_strength: 0.0,
-                texture_path: Some(TextureFaces {
-                    top: "assets/planks_oak.png".to_string(),
-                    bottom: "assets/planks_oak.png".to_string(),
-                    side_x: "assets/planks_oak.png".to_string(),
-                    side_z: "assets/planks_oak.png".to_string(),
-                }),
-            },
+    // Agrega o reemplaza un material; al reemplazarlo conserva el id
+    pub fn insert(&mut self, name: &str, material: Material, block: Option<String>) -> MaterialId {
+        let id = match self.index.get(name) {
+            Some(&id) => {
+                self.materials[id.0 as usize] = material;
+                id
+            }
+            None => {
+                let id = self.add(material);
+                self.names[id.0 as usize] = name.to_string();
+                self.index.insert(name.to_string(), id);
+                id
+            }
+        };
+        let slot = id.0 as usize;
+        if let Some(old) = self.blocks[slot].take() {
+            self.by_block.remove(import::block_name(&old));
+        }
+        if let Some(block) = &block {
+            self.by_block.insert(import::block_name(block).to_string(), id);
+        }
+        self.blocks[slot] = block;
+        id
+    }
 
-            libreria: Material {
-                diffuse: Color::new(162, 130, 78, 255),
-                specular: 20.0,
-                reflectivity: 0.08,
-                transparency: 0.0,
-                refractive_index: 1.0,
-                albedo: [0.85, 0.15],
-                emission: Vector3::zero(),
-                emission_strength: 0.0,
-                texture_path: Some(TextureFaces {
-                    top: "assets/planks_oak.png".to_string(),
-                    bottom: "assets/bookshelf.png".to_string(),
-                    side_x: "assets/bookshelf.png".to_string(),
-                    side_z: "assets/bookshelf.png".to_string(),
-                }),
-            },
+    // Agrega un material sin nombre (no se puede buscar, sólo usar por id)
+    pub fn add(&mut self, material: Material) -> MaterialId {
+        let id = MaterialId(self.materials.len() as u32);
+        self.materials.push(material);
+        self.names.push(String::new());
+        self.blocks.push(None);
+        id
+    }
 
-            flores: Material {
-                diffuse: Color::new(100, 180, 100, 255),
-                specular: 3.0,
-                reflectivity: 0.0,
-                transparency: 0.0,
-                refractive_index: 1.0,
-                albedo: [0.95, 0.05],
-                emission: Vector3::zero(),
-                emission_strength: 0.0,
-                texture_path: Some(TextureFaces {
-                    top: "assets/azalea_leaves_flowers.png".to_string(),
-                    bottom: "assets/azalea_leaves_flowers.png".to_string(),
-                    side_x: "assets/azalea_leaves_flowers.png".to_string(),
-                    side_z: "assets/azalea_leaves_flowers.png".to_string(),
-                }),
-            },
+    pub fn id(&self, name: &str) -> Option<MaterialId> {
+        self.index.get(name).copied()
+    }
 
-            diamante: Material {
-                diffuse: Color::new(180, 240, 255, 255),
-                specular: 200.0,
-                reflectivity: 0.5,
-                transparency: 0.0,
-                refractive_index: 2.42,
-                albedo: [0.4, 0.6],
-                emission: Vector3::zero(),
-                emission_strength: 0.0,
-                texture_path: Some(TextureFaces {
-                    top: "assets/diamond_ore.png".to_string(),
-                    bottom: "assets/diamond_ore.png".to_string(),
-                    side_x: "assets/diamond_ore.png".to_string(),
-                    side_z: "assets/diamond_ore.png".to_string(),
-                }),
-            },
+    pub fn get(&self, name: &str) -> Option<&Material> {
+        self.id(name).map(|id| &self[id])
+    }
 
-            diorita: Material {
-                diffuse: Color::new(200, 200, 200, 255),
-                specular: 50.0,
-                reflectivity: 0.15,
-                transparency: 0.0,
-                refractive_index: 1.0,
-                albedo: [0.75, 0.25],
-                emission: Vector3::zero(),
-                emission_strength: 0.0,
-                texture_path: Some(TextureFaces {
-                    top: "assets/stone_diorite.png".to_string(),
-                    bottom: "assets/stone_diorite.png".to_string(),
-                    side_x: "assets/stone_diorite.png".to_string(),
-                    side_z: "assets/stone_diorite.png".to_string(),
-                }),
-            },
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Material> {
+        self.id(name).map(|id| &mut self.materials[id.0 as usize])
+    }
 
-            roca: Material {
-                diffuse: Color::new(128, 128, 128, 255),
-                specular: 10.0,
-                reflectivity: 0.05,
-                transparency: 0.0,
-                refractive_index: 1.0,
-                albedo: [0.9, 0.1],
-                emission: Vector3::zero(),
-                emission_strength: 0.0,
-                texture_path: Some(TextureFaces {
-                    top: "assets/stone.png".to_string(),
-                    bottom: "assets/stone.png".to_string(),
-                    side_x: "assets/stone.png".to_string(),
-                    side_z: "assets/stone.png".to_string(),
-                }),
-            },
+    // Material cuyo `block` coincide con el id de bloque (sin mirar namespace ni propiedades)
+    pub fn for_block(&self, block_id: &str) -> Option<MaterialId> {
+        self.by_block.get(import::block_name(block_id)).copied()
+    }
 
-            granito: Material {
-                diffuse: Color::new(150, 100, 80, 255),
-                specular: 40.0,
-                reflectivity: 0.12,
-                transparency: 0.0,
-                refractive_index: 1.0,
-                albedo: [0.8, 0.2],
-                emission: Vector3::zero(),
-                emission_strength: 0.0,
-                texture_path: Some(TextureFaces {
-                    top: "assets/stone_granite.png".to_string(),
-                    bottom: "assets/stone_granite.png".to_string(),
-                    side_x: "assets/stone_granite.png".to_string(),
-                    side_z: "assets/stone_granite.png".to_string(),
-                }),
-            },
+    // (nombre, id de bloque) de los materiales que tienen bloque asociado
+    pub fn blocks(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.names
+            .iter()
+            .zip(&self.blocks)
+            .filter_map(|(name, block)| Some((name.as_str(), block.as_deref()?)))
+    }
 
-            dirt_grass: Material {
-                diffuse: Color::new(120, 150, 80, 255),
-                specular: 2.0,
-                reflectivity: 0.0,
-                transparency: 0.0,
-                refractive_index: 1.0,
-                albedo: [0.98, 0.02],
-                emission: Vector3::zero(),
-                emission_strength: 0.0,
-                texture_path: Some(TextureFaces {
-                    top: "assets/grass_path_top.png".to_string(),
-                    bottom: "assets/dirt.png".to_string(),
-                    side_x: "assets/grass_path_side.png".to_string(),
-                    side_z: "assets/grass_path_side.png".to_string(),
-                }),
-            },
+}
 
-            brick: Material {
-                diffuse: Color::new(150, 90, 70, 255),
-                specular: 8.0,
-                reflectivity: 0.02,
-                transparency: 0.0,
-                refractive_index: 1.0,
-                albedo: [0.92, 0.08],
-                emission: Vector3::zero(),
-                emission_strength: 0.0,
-                texture_path: Some(TextureFaces {
-                    top: "assets/brick.png".to_string(),
-                    bottom: "assets/brick.png".to_string(),
-                    side_x: "assets/brick.png".to_string(),
-                    side_z: "assets/brick.png".to_string(),
-                }),
-            },
+impl std::ops::Index<MaterialId> for MaterialLibrary {
+    type Output = Material;
+
+    fn index(&self, id: MaterialId) -> &Material {
+        &self.materials[id.0 as usize]
+    }
+}
 
-            sand: Material {
-                diffuse: Color::new(220, 200, 150, 255),
-                specular: 5.0,
-                reflectivity: 0.0,
-                transparency: 0.0,
-                refractive_index: 1.0,
-                albedo: [0.95, 0.05],
-                emission: Vector3::zero(),
-                emission_strength: 0.0,
-                texture_path: Some(TextureFaces {
-                    top: "assets/sand.png".to_string(),
-                    bottom: "assets/sand.png".to_string(),
-                    side_x: "assets/sand.png".to_string(),
-                    side_z: "assets/sand.png".to_string(),
-                }),
-            },
+impl Default for MaterialLibrary {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-            water: Material {
-                diffuse: Color::new(50, 100, 200, 200),
-                specular: 120.0,
-                reflectivity: 0.35,
-                transparency: 0.6,
-                refractive_index: 1.33,
-                albedo: [0.3, 0.7],
-                emission: Vector3::zero(),
-                emission_strength: 0.0,
-                texture_path: Some(TextureFaces {
-                    top: "assets/water_flow.png".to_string(),
-                    bottom: "assets/water_flow.png".to_string(),
-                    side_x: "assets/water_flow.png".to_string(),
-                    side_z: "assets/water_flow.png".to_string(),
-                }),
-            },
+impl MaterialEntry {
+    // Revisa rangos y que existan las texturas; devuelve el material listo para usar
+    // con sus texturas agregadas a `textures`
+    fn validate(&self, textures: &mut TextureSet) -> Result<Material, String> {
+        let diffuse = match self.diffuse[..] {
+            [r, g, b] => Color::new(r, g, b, 255),
+            [r, g, b, a] => Color::new(r, g, b, a),
+            _ => return Err("`diffuse` debe tener 3 o 4 componentes (0-255)".to_string()),
+        };
 
-            dirt: Material {
-                diffuse: Color::new(134, 96, 67, 255),
-                specular: 2.0,
-                reflectivity: 0.0,
-                transparency: 0.0,
-                refractive_index: 1.0,
-                albedo: [0.98, 0.02],
-                emission: Vector3::zero(),
-                emission_strength: 0.0,
-                texture_path: Some(TextureFaces {
-                    top: "assets/dirt.png".to_string(),
-                    bottom: "assets/dirt.png".to_string(),
-                    side_x: "assets/dirt.png".to_string(),
-                    side_z: "assets/dirt.png".to_string(),
-                }),
-            },
+        let in_unit = |value: f32, field: &str| {
+            if (0.0..=1.0).contains(&value) {
+                Ok(())
+            } else {
+                Err(format!("`{}` debe estar entre 0 y 1 (es {})", field, value))
+            }
+        };
+        in_unit(self.reflectivity, "reflectivity")?;
+        in_unit(self.transparency, "transparency")?;
+        in_unit(self.albedo[0], "albedo[0]")?;
+        in_unit(self.albedo[1], "albedo[1]")?;
+        if self.reflectivity + self.transparency > 1.0 {
+            return Err("`reflectivity` + `transparency` no puede pasar de 1".to_string());
+        }
+        if !(self.specular >= 0.0 && self.specular.is_finite()) {
+            return Err(format!("`specular` debe ser >= 0 (es {})", self.specular));
+        }
+        if !(self.refractive_index >= 1.0 && self.refractive_index.is_finite()) {
+            return Err(format!("`refractive_index` debe ser >= 1 (es {})", self.refractive_index));
+        }
+        if self.emission.iter().any(|c| !(*c >= 0.0 && c.is_finite())) {
+            return Err("`emission` no puede tener componentes negativas".to_string());
+        }
+        if self.absorption.iter().any(|c| !(*c >= 0.0 && c.is_finite())) {
+            return Err("`absorption` no puede tener componentes negativas".to_string());
+        }
+        if !(self.emission_strength >= 0.0 && self.emission_strength.is_finite()) {
+            return Err(format!("`emission_strength` debe ser >= 0 (es {})", self.emission_strength));
+        }
 
-            hierro: Material {
-                diffuse: Color::new(200, 200, 200, 255),
-                specular: 180.0,
-                reflectivity: 0.4,
-                transparency: 0.0,
-                refractive_index: 1.0,
-                albedo: [0.5, 0.5],
-                emission: Vector3::zero(),
-                emission_strength: 0.0,
-                texture_path: Some(TextureFaces {
-                    top: "assets/iron_ore.png".to_string(),
-                    bottom: "assets/iron_ore.png".to_string(),
-                    side_x: "assets/iron_ore.png".to_string(),
-                    side_z: "assets/iron_ore.png".to_string(),
-                }),
-            },
+        let pbr = match &self.pbr {
+            None => None,
+            Some(entry) => {
+                in_unit(entry.metallic, "pbr.metallic")?;
+                in_unit(entry.roughness, "pbr.roughness")?;
+                // `specular` 0.5 = F0 de 0.04, como en el modelo de Disney
+                let f0 = match entry.specular {
+                    Some(specular) => {
+                        in_unit(specular, "pbr.specular")?;
+                        specular * 0.08
+                    }
+                    None if self.refractive_index > 1.0 => Pbr::f0_from_ior(self.refractive_index),
+                    None => 0.04,
+                };
+                Some(Pbr { metallic: entry.metallic, roughness: entry.roughness, f0 })
+            }
+        };
 
-            madera_oscura: Material {
-                diffuse: Color::new(80, 50, 30, 255),
-                specular: 18.0,
-                reflectivity: 0.06,
-                transparency: 0.0,
-                refractive_index: 1.0,
-                albedo: [0.88, 0.12],
-                emission: Vector3::zero(),
-                emission_strength: 0.0,
-                texture_path: Some(TextureFaces {
-                    top: "assets/planks_big_oak.png".to_string(),
-                    bottom: "assets/planks_big_oak.png".to_string(),
-                    side_x: "assets/planks_big_oak.png".to_string(),
-                    side_z: "assets/planks_big_oak.png".to_string(),
-                }),
-            },
+        let faces = match &self.textures {
+            None => None,
+            Some(TexturesEntry::All(path)) => Some(TextureFaces {
+                top: path,
+                bottom: path,
+                side_x: path,
+                side_z: path,
+            }),
+            Some(TexturesEntry::Faces { top, bottom, side_x, side_z }) => Some(TextureFaces { top, bottom, side_x, side_z }),
+        };
+        if let Some(faces) = &faces {
+            // Las rutas son relativas a la carpeta desde la que se ejecuta, como las del skybox
+            for path in [faces.top, faces.bottom, faces.side_x, faces.side_z] {
+                if !Path::new(path).is_file() {
+                    return Err(format!("no existe la textura {}", path));
+                }
+            }
         }
+
+        Ok(Material {
+            diffuse,
+            specular: self.specular,
+            reflectivity: self.reflectivity,
+            transparency: self.transparency,
+            refractive_index: self.refractive_index,
+            albedo: self.albedo,
+            textures: faces.map(|faces| faces.map(|path| textures.intern(path))),
+            emission: Vector3::new(self.emission[0], self.emission[1], self.emission[2]),
+            emission_strength: self.emission_strength,
+            absorption: Vector3::new(self.absorption[0], self.absorption[1], self.absorption[2]),
+            alpha_test: self.alpha_test,
+            pbr,
+        })
     }
-}
\ No newline at end of file
+}
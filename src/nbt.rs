@@ -0,0 +1,245 @@
+// nbt.rs
+// Lector mínimo del formato NBT de Minecraft (el que usan las estructuras,
+// schematics y regiones). Acepta datos sin comprimir, gzip o zlib.
+use flate2::read::{GzDecoder, ZlibDecoder};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Read;
+
+#[derive(Debug, Clone)]
+pub enum Tag {
+    End,
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<i8>),
+    String(String),
+    List(Vec<Tag>),
+    Compound(HashMap<String, Tag>),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+#[derive(Debug)]
+pub struct NbtError(pub String);
+
+impl fmt::Display for NbtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "NBT inválido: {}", self.0)
+    }
+}
+
+impl std::error::Error for NbtError {}
+
+impl Tag {
+    pub fn get(&self, key: &str) -> Option<&Tag> {
+        match self {
+            Tag::Compound(map) => map.get(key),
+            _ => None,
+        }
+    }
+
+    pub fn as_compound(&self) -> Option<&HashMap<String, Tag>> {
+        match self {
+            Tag::Compound(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Tag]> {
+        match self {
+            Tag::List(list) => Some(list),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Tag::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    // Cualquier tag entero como i64 (Minecraft mezcla Byte/Short/Int según la versión)
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Tag::Byte(v) => Some(v as i64),
+            Tag::Short(v) => Some(v as i64),
+            Tag::Int(v) => Some(v as i64),
+            Tag::Long(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_i32(&self) -> Option<i32> {
+        self.as_i64().map(|v| v as i32)
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Tag::Float(v) => Some(v as f64),
+            Tag::Double(v) => Some(v),
+            _ => self.as_i64().map(|v| v as f64),
+        }
+    }
+
+    // Lista de enteros, venga como List de Int o como IntArray
+    pub fn as_int_vec(&self) -> Option<Vec<i32>> {
+        match self {
+            Tag::IntArray(v) => Some(v.clone()),
+            Tag::List(list) => list.iter().map(|t| t.as_i32()).collect(),
+            _ => None,
+        }
+    }
+
+    pub fn as_byte_array(&self) -> Option<&[i8]> {
+        match self {
+            Tag::ByteArray(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_long_array(&self) -> Option<&[i64]> {
+        match self {
+            Tag::LongArray(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+// Descomprime si hace falta (gzip empieza con 1f 8b, zlib con 78)
+pub fn decompress(bytes: &[u8]) -> Result<Vec<u8>, NbtError> {
+    let mut out = Vec::new();
+    if bytes.starts_with(&[0x1f, 0x8b]) {
+        GzDecoder::new(bytes)
+            .read_to_end(&mut out)
+            .map_err(|e| NbtError(format!("gzip: {}", e)))?;
+    } else if bytes.first() == Some(&0x78) {
+        ZlibDecoder::new(bytes)
+            .read_to_end(&mut out)
+            .map_err(|e| NbtError(format!("zlib: {}", e)))?;
+    } else {
+        out.extend_from_slice(bytes);
+    }
+    Ok(out)
+}
+
+// Lee el tag raíz (nombre, valor) de un archivo NBT, comprimido o no
+pub fn read(bytes: &[u8]) -> Result<(String, Tag), NbtError> {
+    let data = decompress(bytes)?;
+    let mut reader = Reader { data: &data, pos: 0 };
+    let id = reader.u8()?;
+    if id != 10 {
+        return Err(NbtError(format!("la raíz debe ser un Compound, no el tag {}", id)));
+    }
+    let name = reader.string()?;
+    let root = reader.payload(id, 0)?;
+    Ok((name, root))
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], NbtError> {
+        if self.pos + n > self.data.len() {
+            return Err(NbtError(format!("fin de datos inesperado en el byte {}", self.pos)));
+        }
+        let slice = &self.data[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8, NbtError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn i16(&mut self) -> Result<i16, NbtError> {
+        Ok(i16::from_be_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn i32(&mut self) -> Result<i32, NbtError> {
+        Ok(i32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn i64(&mut self) -> Result<i64, NbtError> {
+        Ok(i64::from_be_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn len(&mut self) -> Result<usize, NbtError> {
+        let n = self.i32()?;
+        if n < 0 {
+            return Err(NbtError(format!("longitud negativa {}", n)));
+        }
+        Ok(n as usize)
+    }
+
+    fn string(&mut self) -> Result<String, NbtError> {
+        let n = self.i16()? as u16 as usize;
+        // Java usa "modified UTF-8"; para ids de bloques basta con lossy
+        Ok(String::from_utf8_lossy(self.take(n)?).into_owned())
+    }
+
+    fn payload(&mut self, id: u8, depth: usize) -> Result<Tag, NbtError> {
+        if depth > 512 {
+            return Err(NbtError("anidamiento demasiado profundo".to_string()));
+        }
+        Ok(match id {
+            0 => Tag::End,
+            1 => Tag::Byte(self.u8()? as i8),
+            2 => Tag::Short(self.i16()?),
+            3 => Tag::Int(self.i32()?),
+            4 => Tag::Long(self.i64()?),
+            5 => Tag::Float(f32::from_bits(self.i32()? as u32)),
+            6 => Tag::Double(f64::from_bits(self.i64()? as u64)),
+            7 => {
+                let n = self.len()?;
+                Tag::ByteArray(self.take(n)?.iter().map(|&b| b as i8).collect())
+            }
+            8 => Tag::String(self.string()?),
+            9 => {
+                let item = self.u8()?;
+                let n = self.len()?;
+                let mut list = Vec::with_capacity(n.min(1 << 16));
+                for _ in 0..n {
+                    list.push(self.payload(item, depth + 1)?);
+                }
+                Tag::List(list)
+            }
+            10 => {
+                let mut map = HashMap::new();
+                loop {
+                    let child = self.u8()?;
+                    if child == 0 {
+                        break;
+                    }
+                    let name = self.string()?;
+                    map.insert(name, self.payload(child, depth + 1)?);
+                }
+                Tag::Compound(map)
+            }
+            11 => {
+                let n = self.len()?;
+                let mut v = Vec::with_capacity(n.min(1 << 16));
+                for _ in 0..n {
+                    v.push(self.i32()?);
+                }
+                Tag::IntArray(v)
+            }
+            12 => {
+                let n = self.len()?;
+                let mut v = Vec::with_capacity(n.min(1 << 16));
+                for _ in 0..n {
+                    v.push(self.i64()?);
+                }
+                Tag::LongArray(v)
+            }
+            other => return Err(NbtError(format!("tag desconocido {} en el byte {}", other, self.pos))),
+        })
+    }
+}
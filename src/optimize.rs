@@ -0,0 +1,237 @@
+// optimize.rs
+// Junta los bloques vecinos del mismo material en cajas más grandes (greedy meshing en 3D)
+// para que las construcciones importadas bloque por bloque no queden como miles de cubos
+// unitarios. Un bloque opaco rodeado por otros seis opacos no se ve desde ningún lado:
+// puede quedar dentro de una caja de cualquier material y, si ninguna lo cubre, se quita.
+use raylib::prelude::*;
+use std::fmt;
+
+use crate::cube::Cube;
+use crate::material::MaterialId;
+use crate::materials::MaterialLibrary;
+use crate::ray_intersect::RayIntersect;
+use crate::scene::Scene;
+
+// Tamaño máximo de la grilla auxiliar (celdas)
+const MAX_CELLS: usize = 1 << 24;
+
+#[derive(Default, Clone, Copy)]
+pub struct MergeReport {
+    // Bloques que entraron a la grilla
+    pub blocks: usize,
+    // Cajas que los reemplazan
+    pub boxes: usize,
+    // Bloques tapados por todos lados que no quedaron dentro de ninguna caja
+    pub hidden: usize,
+    // Caras entre dos bloques de una misma caja, más las de los bloques ocultos quitados
+    pub inner_faces: usize,
+}
+
+impl MergeReport {
+    pub fn add(&mut self, other: MergeReport) {
+        self.blocks += other.blocks;
+        self.boxes += other.boxes;
+        self.hidden += other.hidden;
+        self.inner_faces += other.inner_faces;
+    }
+
+    // Primitivas menos en la escena
+    pub fn removed(&self) -> usize {
+        self.blocks - self.boxes
+    }
+}
+
+impl fmt::Display for MergeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} bloques -> {} cajas ({} primitivas menos; {} bloques ocultos y {} caras internas quitadas)",
+            self.blocks,
+            self.boxes,
+            self.removed(),
+            self.hidden,
+            self.inner_faces
+        )
+    }
+}
+
+// Grilla densa con el material de cada bloque opaco
+struct Grid {
+    min: Vector3,
+    dims: [usize; 3],
+    cells: Vec<Option<MaterialId>>,
+}
+
+impl Grid {
+    fn index(&self, x: usize, y: usize, z: usize) -> usize {
+        (z * self.dims[1] + y) * self.dims[0] + x
+    }
+
+    // ¿Los seis vecinos son bloques opacos? (fuera de la grilla no hay nada)
+    fn is_hidden(&self, x: usize, y: usize, z: usize) -> bool {
+        let [dx, dy, dz] = self.dims;
+        if x == 0 || y == 0 || z == 0 || x + 1 == dx || y + 1 == dy || z + 1 == dz {
+            return false;
+        }
+        [
+            (x - 1, y, z),
+            (x + 1, y, z),
+            (x, y - 1, z),
+            (x, y + 1, z),
+            (x, y, z - 1),
+            (x, y, z + 1),
+        ]
+        .iter()
+        .all(|&(nx, ny, nz)| self.cells[self.index(nx, ny, nz)].is_some())
+    }
+}
+
+// Reemplaza los cubos opacos de `cell_size` sin rotación y alineados a la grilla por cajas
+// del mismo material. Los transparentes se dejan sueltos: juntarlos quitaría las caras
+// internas en las que hoy refracta el rayo. Tampoco se juntan los de alpha test (hojas),
+// que dejan ver lo que tienen detrás.
+pub fn merge_blocks(scene: &mut Scene, materials: &MaterialLibrary, cell_size: f32) -> MergeReport {
+    let half = cell_size * 0.5;
+    let near = |a: f32, b: f32| (a - b).abs() < 1e-3;
+    let is_multiple = |v: f32| {
+        let cells = v / cell_size;
+        (cells - cells.round()).abs() < 1e-3
+    };
+    let is_block = |c: &Cube| {
+        c.rot_x == 0.0
+            && c.rot_y == 0.0
+            && c.uv_offset == Vector3::zero()
+            && near(c.half_size.x, half)
+            && near(c.half_size.y, half)
+            && near(c.half_size.z, half)
+            && materials[c.material].transparency <= 0.0
+            && !materials[c.material].alpha_test
+    };
+
+    let Some(anchor) = scene.objects.iter().filter_map(|o| o.as_cube()).find(|c| is_block(c)).map(|c| c.center) else {
+        return MergeReport::default();
+    };
+    let aligned = |c: &Cube| {
+        let offset = c.center - anchor;
+        is_block(c) && is_multiple(offset.x) && is_multiple(offset.y) && is_multiple(offset.z)
+    };
+
+    let mut lo = Vector3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+    let mut hi = Vector3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY);
+    for cube in scene.objects.iter().filter_map(|o| o.as_cube()).filter(|c| aligned(c)) {
+        lo = Vector3::new(lo.x.min(cube.center.x), lo.y.min(cube.center.y), lo.z.min(cube.center.z));
+        hi = Vector3::new(hi.x.max(cube.center.x), hi.y.max(cube.center.y), hi.z.max(cube.center.z));
+    }
+    let dims = [
+        ((hi.x - lo.x) / cell_size).round() as usize + 1,
+        ((hi.y - lo.y) / cell_size).round() as usize + 1,
+        ((hi.z - lo.z) / cell_size).round() as usize + 1,
+    ];
+    if dims[0] * dims[1] * dims[2] > MAX_CELLS {
+        return MergeReport::default();
+    }
+
+    let mut grid = Grid {
+        min: lo - Vector3::new(half, half, half),
+        dims,
+        cells: vec![None; dims[0] * dims[1] * dims[2]],
+    };
+    let mut report = MergeReport::default();
+    let mut rest: Vec<Box<dyn RayIntersect>> = Vec::new();
+    for object in scene.objects.drain(..) {
+        let cell = object.as_cube().filter(|c| aligned(c)).map(|cube| {
+            let p = cube.center - lo;
+            let index = grid.index(
+                (p.x / cell_size).round() as usize,
+                (p.y / cell_size).round() as usize,
+                (p.z / cell_size).round() as usize,
+            );
+            (index, cube.material)
+        });
+        match cell {
+            // Un cubo repetido en la misma celda se deja como está
+            Some((index, material)) if grid.cells[index].is_none() => {
+                grid.cells[index] = Some(material);
+                report.blocks += 1;
+            }
+            _ => rest.push(object),
+        }
+    }
+
+    let [dx, dy, dz] = dims;
+    let mut hidden = vec![false; grid.cells.len()];
+    for z in 0..dz {
+        for y in 0..dy {
+            for x in 0..dx {
+                let index = grid.index(x, y, z);
+                hidden[index] = grid.cells[index].is_some() && grid.is_hidden(x, y, z);
+            }
+        }
+    }
+
+    // Cada bloque visible todavía libre empieza una caja que crece en x, después en z y
+    // después en y mientras todas las celdas nuevas sean de su material o estén ocultas
+    let mut used = vec![false; grid.cells.len()];
+    let mut boxes = Vec::new();
+    for z0 in 0..dz {
+        for y0 in 0..dy {
+            for x0 in 0..dx {
+                let seed = grid.index(x0, y0, z0);
+                let Some(material) = grid.cells[seed] else { continue };
+                if used[seed] || hidden[seed] {
+                    continue;
+                }
+                let fits = |x: usize, y: usize, z: usize| {
+                    let index = grid.index(x, y, z);
+                    !used[index] && (grid.cells[index] == Some(material) || hidden[index])
+                };
+
+                let mut x1 = x0;
+                while x1 + 1 < dx && fits(x1 + 1, y0, z0) {
+                    x1 += 1;
+                }
+                let mut z1 = z0;
+                while z1 + 1 < dz && (x0..=x1).all(|x| fits(x, y0, z1 + 1)) {
+                    z1 += 1;
+                }
+                let mut y1 = y0;
+                while y1 + 1 < dy && (z0..=z1).all(|z| (x0..=x1).all(|x| fits(x, y1 + 1, z))) {
+                    y1 += 1;
+                }
+
+                for z in z0..=z1 {
+                    for y in y0..=y1 {
+                        for x in x0..=x1 {
+                            used[grid.index(x, y, z)] = true;
+                        }
+                    }
+                }
+                let (sx, sy, sz) = (x1 - x0 + 1, y1 - y0 + 1, z1 - z0 + 1);
+                report.inner_faces += 2 * ((sx - 1) * sy * sz + sx * (sy - 1) * sz + sx * sy * (sz - 1));
+
+                let size = Vector3::new(sx as f32, sy as f32, sz as f32) * cell_size;
+                let corner = grid.min + Vector3::new(x0 as f32, y0 as f32, z0 as f32) * cell_size;
+                let half_size = size * 0.5;
+                boxes.push(Cube {
+                    center: corner + half_size,
+                    half_size,
+                    rot_x: 0.0,
+                    rot_y: 0.0,
+                    material,
+                    // Que cada bloque de la caja repita la textura como cuando estaba suelto
+                    uv_offset: half_size - Vector3::new(half, half, half),
+                });
+            }
+        }
+    }
+
+    report.hidden = hidden.iter().zip(&used).filter(|&(&h, &u)| h && !u).count();
+    report.inner_faces += report.hidden * 6;
+    report.boxes = boxes.len();
+
+    scene.objects = rest;
+    for cube in boxes {
+        scene.add_cube(cube);
+    }
+    report
+}
@@ -0,0 +1,74 @@
+// packet.rs
+// Paquetes de LANES rayos que recorren el BVH juntos: los rayos primarios de píxeles
+// vecinos (y sus sombras hacia una misma luz) van casi en la misma dirección, así que
+// cada nodo se prueba una sola vez para los cuatro. Las cuentas se hacen carril por
+// carril sobre arreglos de tamaño fijo, que el compilador vectoriza (SSE/NEON) sin
+// depender de std::simd ni de intrínsecos de una arquitectura.
+use raylib::prelude::*;
+
+use crate::bvh::Aabb;
+
+pub const LANES: usize = 4;
+
+#[derive(Clone, Copy)]
+pub struct RayPacket {
+    pub origins: [Vector3; LANES],
+    pub directions: [Vector3; LANES],
+    // Los carriles apagados (p. ej. al final de una fila) no tocan nada
+    pub active: [bool; LANES],
+    // Las mismas componentes por eje (SoA) para las pruebas contra cajas
+    ox: [f32; LANES],
+    oy: [f32; LANES],
+    oz: [f32; LANES],
+    inv_x: [f32; LANES],
+    inv_y: [f32; LANES],
+    inv_z: [f32; LANES],
+}
+
+impl RayPacket {
+    pub fn new(origins: [Vector3; LANES], directions: [Vector3; LANES], active: [bool; LANES]) -> Self {
+        RayPacket {
+            origins,
+            directions,
+            active,
+            ox: origins.map(|o| o.x),
+            oy: origins.map(|o| o.y),
+            oz: origins.map(|o| o.z),
+            inv_x: directions.map(|d| 1.0 / d.x),
+            inv_y: directions.map(|d| 1.0 / d.y),
+            inv_z: directions.map(|d| 1.0 / d.z),
+        }
+    }
+
+    // El mismo paquete con solo los carriles de `mask` encendidos
+    pub fn masked(&self, mask: [bool; LANES]) -> RayPacket {
+        RayPacket { active: std::array::from_fn(|lane| self.active[lane] && mask[lane]), ..*self }
+    }
+
+    // Igual que Aabb::hit para cada carril: qué rayos entran a la caja antes de max_t[i],
+    // y la menor distancia de entrada entre ellos (para ordenar los hijos del BVH)
+    pub fn hit_aabb(&self, bounds: &Aabb, max_t: &[f32; LANES]) -> ([bool; LANES], f32) {
+        let mut t_near = [0.0f32; LANES];
+        let mut t_far = [0.0f32; LANES];
+        for i in 0..LANES {
+            let tx1 = (bounds.min.x - self.ox[i]) * self.inv_x[i];
+            let tx2 = (bounds.max.x - self.ox[i]) * self.inv_x[i];
+            let ty1 = (bounds.min.y - self.oy[i]) * self.inv_y[i];
+            let ty2 = (bounds.max.y - self.oy[i]) * self.inv_y[i];
+            let tz1 = (bounds.min.z - self.oz[i]) * self.inv_z[i];
+            let tz2 = (bounds.max.z - self.oz[i]) * self.inv_z[i];
+            t_near[i] = tx1.min(tx2).max(ty1.min(ty2)).max(tz1.min(tz2));
+            t_far[i] = tx1.max(tx2).min(ty1.max(ty2)).min(tz1.max(tz2));
+        }
+
+        let mut mask = [false; LANES];
+        let mut nearest = f32::INFINITY;
+        for i in 0..LANES {
+            mask[i] = self.active[i] && t_far[i] >= t_near[i] && t_far[i] >= 0.0 && t_near[i] <= max_t[i];
+            if mask[i] {
+                nearest = nearest.min(t_near[i].max(0.0));
+            }
+        }
+        (mask, nearest)
+    }
+}
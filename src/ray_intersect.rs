@@ -1,5 +1,7 @@
 use raylib::prelude::*;
-use crate::material::Material;
+use crate::bvh::Aabb;
+use crate::cube::Cube;
+use crate::material::MaterialId;
 
 pub struct HitInfo {
     pub hit: bool,
@@ -8,8 +10,8 @@ pub struct HitInfo {
     pub normal: Vector3,       // normal en espacio mundo
     pub local_normal: Vector3, // normal en espacio local (útil para decidir cara)
     pub distance: f32,
-    pub material: Material,
-    pub texture_path: Option<String>,
+    // La textura de la cara sale del material y de local_normal al sombrear
+    pub material: MaterialId,
     
 
 }
@@ -20,4 +22,38 @@ pub trait RayIntersect: Send + Sync {
         ray_origin: &Vector3,
         ray_direction: &Vector3
     ) -> Option<HitInfo>;
+
+    // Caja en espacio mundo que contiene al objeto (para el BVH)
+    fn aabb(&self) -> Aabb;
+
+    // Acceso al cubo concreto si el objeto lo es
+    fn as_cube(&self) -> Option<&Cube> {
+        None
+    }
+
+    // Para el exportador: recorre el objeto como cubos sueltos
+    fn for_each_cube(&self, f: &mut dyn FnMut(&Cube)) {
+        if let Some(cube) = self.as_cube() {
+            f(cube);
+        }
+    }
+}
+
+// Una lista de Box<dyn RayIntersect> y una de cubos se recorren con el mismo código del BVH
+impl<T: RayIntersect + ?Sized> RayIntersect for Box<T> {
+    fn ray_intersect(&self, ray_origin: &Vector3, ray_direction: &Vector3) -> Option<HitInfo> {
+        (**self).ray_intersect(ray_origin, ray_direction)
+    }
+
+    fn aabb(&self) -> Aabb {
+        (**self).aabb()
+    }
+
+    fn as_cube(&self) -> Option<&Cube> {
+        (**self).as_cube()
+    }
+
+    fn for_each_cube(&self, f: &mut dyn FnMut(&Cube)) {
+        (**self).for_each_cube(f)
+    }
 }
@@ -0,0 +1,43 @@
+// resolution.rs
+// Resolución dinámica: mientras la cámara se mueve se traza un rayo por bloque de
+// píxeles en lugar de uno por píxel, con el bloque más chico que entra en el tiempo
+// por cuadro pedido. Con la cámara quieta se vuelve a la resolución completa.
+use std::time::Duration;
+
+// Bloque más grande que se usa aunque no se llegue al tiempo pedido
+pub const MAX_BLOCK: usize = 8;
+
+pub struct ResolutionController {
+    target: Duration,
+    // Segundos por rayo primario, promediado entre los últimos cuadros
+    cost_per_ray: Option<f64>,
+}
+
+impl ResolutionController {
+    pub fn new(target: Duration) -> Self {
+        ResolutionController { target, cost_per_ray: None }
+    }
+
+    // Registra cuánto tardó un cuadro en trazar `rays` rayos primarios
+    pub fn record(&mut self, rays: usize, elapsed: Duration) {
+        if rays == 0 {
+            return;
+        }
+        let cost = elapsed.as_secs_f64() / rays as f64;
+        // Promedio móvil para que un cuadro suelto más lento no haga saltar la resolución
+        self.cost_per_ray = Some(match self.cost_per_ray {
+            Some(previous) => previous * 0.7 + cost * 0.3,
+            None => cost,
+        });
+    }
+
+    // Lado del bloque (1 = resolución completa) para una imagen de `width` x `height`:
+    // el más chico con el que el cuadro estimado no pasa del tiempo pedido
+    pub fn block(&self, width: usize, height: usize) -> usize {
+        let Some(cost) = self.cost_per_ray else { return 1 };
+        let target = self.target.as_secs_f64();
+        (1..MAX_BLOCK)
+            .find(|&block| (width.div_ceil(block) * height.div_ceil(block)) as f64 * cost <= target)
+            .unwrap_or(MAX_BLOCK)
+    }
+}
@@ -0,0 +1,241 @@
+// resource_pack.rs
+// Lee un resource pack de Minecraft Java descomprimido y resuelve
+// blockstates/*.json -> models/block/*.json -> texturas, para generar los
+// TextureFaces de cada bloque sin escribir rutas a mano.
+// Se pueden apilar varios packs: el primero tiene prioridad y los demás
+// (p. ej. el pack vanilla) completan lo que falte.
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::material::{Material, TextureFaces};
+use crate::materials::MaterialLibrary;
+use crate::textures::TextureSet;
+
+#[derive(Debug)]
+pub struct PackError(pub String);
+
+impl fmt::Display for PackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for PackError {}
+
+pub struct ResourcePack {
+    // Carpetas raíz de cada pack (las que contienen `assets/`), en orden de prioridad
+    layers: Vec<PathBuf>,
+}
+
+// Modelo con la herencia de `parent` ya aplicada
+struct ResolvedModel {
+    textures: HashMap<String, String>,
+    elements: Option<Vec<Value>>,
+}
+
+impl ResourcePack {
+    pub fn open(roots: &[PathBuf]) -> Result<Self, PackError> {
+        if roots.is_empty() {
+            return Err(PackError("no se indicó ningún resource pack".to_string()));
+        }
+        for root in roots {
+            if !root.join("assets").is_dir() {
+                return Err(PackError(format!(
+                    "{}: no parece un resource pack (falta la carpeta assets/)",
+                    root.display()
+                )));
+            }
+        }
+        Ok(ResourcePack { layers: roots.to_vec() })
+    }
+
+    // Primer archivo existente entre las capas
+    fn find(&self, relative: &Path) -> Option<PathBuf> {
+        self.layers
+            .iter()
+            .map(|root| root.join(relative))
+            .find(|path| path.is_file())
+    }
+
+    fn read_json(&self, relative: &Path) -> Result<Value, PackError> {
+        let path = self
+            .find(relative)
+            .ok_or_else(|| PackError(format!("no existe {} en ningún pack", relative.display())))?;
+        let text = std::fs::read_to_string(&path).map_err(|e| PackError(format!("{}: {}", path.display(), e)))?;
+        serde_json::from_str(&text).map_err(|e| PackError(format!("{}: {}", path.display(), e)))
+    }
+
+    // Texturas por cara para un id de bloque, p. ej. "minecraft:furnace" o "minecraft:redstone_lamp[lit=true]"
+    pub fn block_faces(&self, block_id: &str) -> Result<TextureFaces<String>, PackError> {
+        let (namespace, name) = split_id(block_id.split('[').next().unwrap_or(block_id));
+        let properties = parse_properties(block_id);
+
+        let blockstate = self.read_json(&Path::new("assets").join(namespace).join("blockstates").join(format!("{}.json", name)))?;
+        let model_id = pick_model(&blockstate, &properties)
+            .ok_or_else(|| PackError(format!("{}: el blockstate no tiene modelos", block_id)))?;
+        let model = self.resolve_model(&model_id, 0)?;
+
+        let texture = |key: &str| -> Option<String> {
+            resolve_variable(&model.textures, key).and_then(|reference| self.texture_path(&reference))
+        };
+
+        // Caras de los elements (el primer element que define cada cara)
+        let face = |direction: &str| -> Option<String> {
+            model.elements.as_ref()?.iter().find_map(|element| {
+                let reference = element.get("faces")?.get(direction)?.get("texture")?.as_str()?;
+                texture(reference)
+            })
+        };
+
+        // Si el modelo no tiene elements (agua, lava...) se usan las variables de textura comunes
+        let fallback = |keys: &[&str]| keys.iter().find_map(|key| texture(&format!("#{}", key)));
+
+        let top = face("up").or_else(|| fallback(&["up", "top", "end", "all", "particle"]));
+        let bottom = face("down").or_else(|| fallback(&["down", "bottom", "end", "all", "particle"]));
+        let side_x = face("east").or_else(|| face("west")).or_else(|| fallback(&["side", "all", "particle"]));
+        let side_z = face("south").or_else(|| face("north")).or_else(|| fallback(&["side", "all", "particle"]));
+
+        match (top, bottom, side_x, side_z) {
+            (Some(top), Some(bottom), Some(side_x), Some(side_z)) => Ok(TextureFaces { top, bottom, side_x, side_z }),
+            _ => Err(PackError(format!("{}: no se pudieron resolver las texturas del modelo {}", block_id, model_id))),
+        }
+    }
+
+    // Material para un bloque: los parámetros físicos de `base` con las texturas del pack
+    pub fn material(&self, block_id: &str, base: &Material, textures: &mut TextureSet) -> Result<Material, PackError> {
+        let faces = self.block_faces(block_id)?;
+        Ok(Material {
+            textures: Some(faces.map(|path| textures.intern(path))),
+            ..*base
+        })
+    }
+
+    // Reemplaza las texturas de los materiales que tienen `block`; devuelve avisos por los que fallen
+    pub fn apply(&self, materials: &mut MaterialLibrary) -> Vec<String> {
+        let mut warnings = Vec::new();
+        let mut resolved = Vec::new();
+        for (name, block_id) in materials.blocks() {
+            match self.block_faces(block_id) {
+                Ok(faces) => resolved.push((name.to_string(), faces)),
+                Err(e) => warnings.push(format!("{}: {}", name, e)),
+            }
+        }
+        for (name, faces) in resolved {
+            let faces = faces.map(|path| materials.textures.intern(path));
+            if let Some(material) = materials.get_mut(&name) {
+                material.textures = Some(faces);
+            }
+        }
+        warnings
+    }
+
+    fn resolve_model(&self, model_id: &str, depth: usize) -> Result<ResolvedModel, PackError> {
+        if depth > 32 {
+            return Err(PackError(format!("{}: cadena de `parent` demasiado larga", model_id)));
+        }
+        let (namespace, path) = split_id(model_id);
+        // Modelos internos del juego: no tienen archivo
+        if namespace == "builtin" || path.starts_with("builtin/") {
+            return Ok(ResolvedModel { textures: HashMap::new(), elements: None });
+        }
+
+        let json = self.read_json(&Path::new("assets").join(namespace).join("models").join(format!("{}.json", path)))?;
+
+        let mut model = match json.get("parent").and_then(|p| p.as_str()) {
+            Some(parent) => self.resolve_model(parent, depth + 1)?,
+            None => ResolvedModel { textures: HashMap::new(), elements: None },
+        };
+
+        // Las texturas del hijo pisan las del padre
+        if let Some(textures) = json.get("textures").and_then(|t| t.as_object()) {
+            for (key, value) in textures {
+                if let Some(value) = value.as_str() {
+                    model.textures.insert(key.clone(), value.to_string());
+                }
+            }
+        }
+        // Los elements del hijo reemplazan por completo a los del padre
+        if let Some(elements) = json.get("elements").and_then(|e| e.as_array()) {
+            model.elements = Some(elements.clone());
+        }
+        Ok(model)
+    }
+
+    // "minecraft:block/stone" -> ruta del .png en el primer pack que la tenga
+    fn texture_path(&self, reference: &str) -> Option<String> {
+        let (namespace, path) = split_id(reference);
+        let relative = Path::new("assets").join(namespace).join("textures").join(format!("{}.png", path));
+        self.find(&relative).map(|p| p.to_string_lossy().into_owned())
+    }
+}
+
+// "minecraft:block/stone" -> ("minecraft", "block/stone"); sin namespace se asume minecraft
+fn split_id(id: &str) -> (&str, &str) {
+    match id.split_once(':') {
+        Some((namespace, path)) => (namespace, path),
+        None => ("minecraft", id),
+    }
+}
+
+// "minecraft:furnace[facing=north,lit=true]" -> {facing: north, lit: true}
+fn parse_properties(block_id: &str) -> HashMap<String, String> {
+    let inner = block_id
+        .split_once('[')
+        .map(|(_, rest)| rest.trim_end_matches(']'))
+        .unwrap_or("");
+    inner
+        .split(',')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+        .collect()
+}
+
+// Elige el modelo de un blockstate según las propiedades pedidas
+fn pick_model(blockstate: &Value, properties: &HashMap<String, String>) -> Option<String> {
+    // Una variante puede ser un objeto o una lista de objetos con peso; usamos el primero
+    let model_of = |variant: &Value| -> Option<String> {
+        let variant = match variant {
+            Value::Array(list) => list.first()?,
+            other => other,
+        };
+        variant.get("model")?.as_str().map(|s| s.to_string())
+    };
+
+    if let Some(variants) = blockstate.get("variants").and_then(|v| v.as_object()) {
+        let matches = |key: &str| {
+            key.split(',')
+                .filter(|pair| !pair.is_empty())
+                .filter_map(|pair| pair.split_once('='))
+                .all(|(k, v)| properties.get(k).is_none_or(|wanted| wanted == v))
+        };
+        let mut keys: Vec<&String> = variants.keys().collect();
+        keys.sort();
+        let key = keys
+            .iter()
+            .find(|k| matches(k))
+            .or_else(|| keys.first())?;
+        return model_of(&variants[key.as_str()]);
+    }
+
+    // Multipart: el primer `apply` (normalmente la parte base del bloque)
+    blockstate
+        .get("multipart")?
+        .as_array()?
+        .iter()
+        .find_map(|part| model_of(part.get("apply")?))
+}
+
+// Sigue las referencias "#variable" hasta llegar a una textura
+fn resolve_variable(textures: &HashMap<String, String>, reference: &str) -> Option<String> {
+    let mut current = reference.to_string();
+    for _ in 0..16 {
+        match current.strip_prefix('#') {
+            Some(variable) => current = textures.get(variable)?.clone(),
+            None => return Some(current),
+        }
+    }
+    None
+}
+
@@ -1,21 +1,326 @@
 use raylib::prelude::*;
 use crate::cube::Cube;
-use crate::material::Material;
-use crate::ray_intersect::RayIntersect;
+use crate::material::MaterialId;
+use crate::bvh::{nearest, Aabb, Bvh};
+use crate::instance::Instance;
+use crate::packet::{RayPacket, LANES};
+use crate::ray_intersect::{HitInfo, RayIntersect};
+use crate::svo::{OctreeBuilder, SparseVoxelOctree, MAX_DEPTH};
+use crate::voxel_grid::VoxelGrid;
 
+// Tamaño máximo de la grilla que arma voxelize (celdas)
+const MAX_GRID_CELLS: usize = 1 << 24;
+
+// Cubo animado que gira a velocidad constante (`spin` en el archivo de escena)
+#[derive(Clone, Copy)]
+pub struct Spin {
+    // Índice en los cubos dinámicos de la escena
+    pub cube: usize,
+    // Rotación en t = 0
+    pub rot_x: f32,
+    pub rot_y: f32,
+    // Radianes por segundo en cada eje
+    pub speed: [f32; 2],
+}
+
+// Cómo guarda voxelize los bloques alineados
+#[derive(Clone, Copy)]
+pub enum VoxelLayout {
+    // Grilla densa: rápida, pero ocupa memoria por cada celda de la caja
+    Grid,
+    // Octree disperso: para mundos grandes con mucho espacio vacío
+    Octree,
+}
+
+// BVH de dos niveles. Abajo (BLAS) cada grupo de objetos tiene su propio BVH: todos los
+// fijos (terreno, construcciones) forman uno y cada cubo animado es uno de un solo objeto.
+// Arriba (TLAS) hay un BVH sobre las cajas de los BLAS. Al animar solo se recalculan las
+// cajas del TLAS con refit(); el BVH de los objetos fijos no se toca.
 pub struct Scene {
     pub objects: Vec<Box<dyn RayIntersect>>,
+    // BLAS de los objetos fijos. Se arma con build_bvh() cuando la escena ya está completa;
+    // si no hay, se recorre todo
+    bvh: Option<Bvh>,
+    // Caja de la raíz del BLAS de los objetos fijos
+    fixed_bounds: Aabb,
+    dynamic: Vec<Cube>,
+    // TLAS: la hoja 0 es el BLAS de los objetos fijos y la i + 1 el del cubo animado i
+    tlas: Option<Bvh>,
 }
 
+// Hoja del TLAS con los objetos fijos
+const FIXED: u32 = 0;
+
 impl Scene {
     pub fn new() -> Self {
         Scene {
             objects: Vec::new(),
+            bvh: None,
+            fixed_bounds: Aabb::empty(),
+            dynamic: Vec::new(),
+            tlas: None,
         }
     }
 
     pub fn add_cube(&mut self, cube: Cube) {
         self.objects.push(Box::new(cube));
+        // El BVH anterior ya no cubre todos los objetos
+        self.clear_bvh();
+    }
+
+    pub fn add_voxel_grid(&mut self, grid: VoxelGrid) {
+        self.objects.push(Box::new(grid));
+        self.clear_bvh();
+    }
+
+    pub fn add_voxel_octree(&mut self, octree: SparseVoxelOctree) {
+        self.objects.push(Box::new(octree));
+        self.clear_bvh();
+    }
+
+    pub fn add_instance(&mut self, instance: Instance) {
+        self.objects.push(Box::new(instance));
+        self.clear_bvh();
+    }
+
+    // Agrega un cubo que se va a mover; devuelve su índice en dynamic_cubes()
+    pub fn add_dynamic_cube(&mut self, cube: Cube) -> usize {
+        self.dynamic.push(cube);
+        self.tlas = None;
+        self.dynamic.len() - 1
+    }
+
+    fn clear_bvh(&mut self) {
+        self.bvh = None;
+        self.tlas = None;
+    }
+
+    pub fn dynamic_cubes(&self) -> &[Cube] {
+        &self.dynamic
+    }
+
+    // Después de mover los cubos hay que llamar a refit()
+    pub fn dynamic_cubes_mut(&mut self) -> &mut [Cube] {
+        &mut self.dynamic
+    }
+
+    // Cajas de las hojas del TLAS: los objetos fijos y después cada cubo animado
+    fn blas_bounds(&self) -> Vec<Aabb> {
+        std::iter::once(self.fixed_bounds).chain(self.dynamic.iter().map(|c| c.aabb())).collect()
+    }
+
+    // Reajusta el TLAS a las posiciones actuales de los cubos dinámicos (sin BVH de los objetos
+    // fijos todavía no hay TLAS y las consultas recorren todo)
+    pub fn refit(&mut self) {
+        let bounds = self.blas_bounds();
+        match &mut self.tlas {
+            Some(tlas) => tlas.refit(&bounds),
+            None if self.bvh.is_some() => self.tlas = Some(Bvh::build(&bounds)),
+            None => {}
+        }
+    }
+
+    // Gira los cubos animados a su rotación en `time` segundos y reajusta el TLAS
+    pub fn animate(&mut self, spins: &[Spin], time: f32) {
+        for spin in spins {
+            let cube = &mut self.dynamic[spin.cube];
+            cube.rot_x = spin.rot_x + spin.speed[0] * time;
+            cube.rot_y = spin.rot_y + spin.speed[1] * time;
+        }
+        self.refit();
+    }
+
+    // Junta los cubos sin rotación alineados a una grilla de `cell_size` en un solo
+    // VoxelGrid u octree; los demás quedan como están. Devuelve cuántos cubos se convirtieron.
+    pub fn voxelize(&mut self, cell_size: f32, layout: VoxelLayout) -> usize {
+        let is_multiple = |v: f32| {
+            let cells = v / cell_size;
+            (cells - cells.round()).abs() < 1e-3
+        };
+        let min_corner = |c: &Cube| c.center - c.half_size;
+        let fits = |c: &Cube| {
+            c.rot_x == 0.0
+                && c.rot_y == 0.0
+                && is_multiple(c.half_size.x * 2.0)
+                && is_multiple(c.half_size.y * 2.0)
+                && is_multiple(c.half_size.z * 2.0)
+        };
+
+        // La grilla se alinea con la esquina del primer cubo que sirve
+        let Some(anchor) = self.objects.iter().filter_map(|o| o.as_cube()).find(|c| fits(c)).map(min_corner) else {
+            return 0;
+        };
+        let aligned = |c: &Cube| {
+            let offset = min_corner(c) - anchor;
+            fits(c) && is_multiple(offset.x) && is_multiple(offset.y) && is_multiple(offset.z)
+        };
+
+        let mut min = Vector3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+        let mut max = Vector3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY);
+        for cube in self.objects.iter().filter_map(|o| o.as_cube()).filter(|c| aligned(c)) {
+            let (lo, hi) = (cube.center - cube.half_size, cube.center + cube.half_size);
+            min = Vector3::new(min.x.min(lo.x), min.y.min(lo.y), min.z.min(lo.z));
+            max = Vector3::new(max.x.max(hi.x), max.y.max(hi.y), max.z.max(hi.z));
+        }
+        let dims = [
+            ((max.x - min.x) / cell_size).round() as usize,
+            ((max.y - min.y) / cell_size).round() as usize,
+            ((max.z - min.z) / cell_size).round() as usize,
+        ];
+
+        let mut converted = 0;
+        let mut rest: Vec<Box<dyn RayIntersect>> = Vec::new();
+        match layout {
+            VoxelLayout::Grid => {
+                if dims[0] * dims[1] * dims[2] > MAX_GRID_CELLS {
+                    return 0;
+                }
+                let mut grid = VoxelGrid::new(min, cell_size, dims);
+                for object in self.objects.drain(..) {
+                    match object.as_cube() {
+                        Some(cube) if aligned(cube) && grid.add_cube(cube) => converted += 1,
+                        _ => rest.push(object),
+                    }
+                }
+                self.objects = rest;
+                self.add_voxel_grid(grid);
+            }
+            VoxelLayout::Octree => {
+                if dims.iter().any(|d| *d > 1 << MAX_DEPTH) {
+                    return 0;
+                }
+                let mut builder = OctreeBuilder::new(min, cell_size);
+                for object in self.objects.drain(..) {
+                    match object.as_cube() {
+                        Some(cube) if aligned(cube) && builder.add_cube(cube) => converted += 1,
+                        _ => rest.push(object),
+                    }
+                }
+                self.objects = rest;
+                self.add_voxel_octree(builder.build());
+            }
+        }
+        converted
+    }
+
+    pub fn build_bvh(&mut self) {
+        let bounds: Vec<_> = self.objects.iter().map(|o| o.aabb()).collect();
+        self.fixed_bounds = bounds.iter().fold(Aabb::empty(), |acc, b| acc.union(b));
+        self.bvh = Some(Bvh::build(&bounds));
+        self.tlas = Some(Bvh::build(&self.blas_bounds()));
+    }
+
+    // Caja que contiene a todos los objetos (vacía si no hay ninguno)
+    pub fn bounds(&self) -> Aabb {
+        let fixed = self.objects.iter().fold(Aabb::empty(), |bounds, o| bounds.union(&o.aabb()));
+        self.dynamic.iter().fold(fixed, |bounds, c| bounds.union(&c.aabb()))
+    }
+
+    pub fn has_bvh(&self) -> bool {
+        self.bvh.is_some()
+    }
+
+    // Impacto más cercano del rayo con la escena
+    pub fn closest_hit(&self, origin: &Vector3, direction: &Vector3) -> Option<HitInfo> {
+        let Some(tlas) = &self.tlas else {
+            return self.closest_hit_linear(origin, direction);
+        };
+        tlas.closest_hit_with(origin, direction, |blas| match blas {
+            FIXED => closest_hit(&self.objects, self.bvh.as_ref(), origin, direction),
+            cube => self.dynamic[cube as usize - 1].ray_intersect(origin, direction),
+        })
+    }
+
+    // ¿Algún objeto entre origin y origin + direction * max_dist? (rayos de sombra)
+    pub fn any_hit(&self, origin: &Vector3, direction: &Vector3, max_dist: f32) -> bool {
+        self.first_hit(origin, direction, max_dist).is_some()
+    }
+
+    // El primer impacto que se encuentre antes de max_dist (no necesariamente el más
+    // cercano), para revisar qué tapa el rayo
+    pub fn first_hit(&self, origin: &Vector3, direction: &Vector3, max_dist: f32) -> Option<HitInfo> {
+        let Some(tlas) = &self.tlas else {
+            return first_hit_linear(&self.objects, origin, direction, max_dist)
+                .or_else(|| first_hit_linear(&self.dynamic, origin, direction, max_dist));
+        };
+        tlas.first_hit_with(origin, direction, max_dist, |blas| match blas {
+            FIXED => first_hit(&self.objects, self.bvh.as_ref(), origin, direction, max_dist),
+            cube => self.dynamic[cube as usize - 1].ray_intersect(origin, direction),
+        })
+    }
+
+    // closest_hit para un paquete de rayos vecinos: baja junto por el TLAS y por el BLAS de
+    // los objetos fijos con los carriles que llegan a él
+    pub fn closest_hit_packet(&self, packet: &RayPacket) -> [Option<HitInfo>; LANES] {
+        let Some(tlas) = &self.tlas else {
+            return std::array::from_fn(|lane| {
+                packet.active[lane]
+                    .then(|| self.closest_hit_linear(&packet.origins[lane], &packet.directions[lane]))
+                    .flatten()
+            });
+        };
+        tlas.closest_hit_packet_with(packet, |blas, mask, closest| match blas {
+            FIXED => {
+                let hits = closest_hit_packet(&self.objects, self.bvh.as_ref(), &packet.masked(mask));
+                for (hit, other) in closest.iter_mut().zip(hits) {
+                    *hit = nearest(hit.take(), other);
+                }
+            }
+            cube => {
+                let cube = &self.dynamic[cube as usize - 1];
+                for lane in (0..LANES).filter(|&lane| mask[lane]) {
+                    let hit = cube.ray_intersect(&packet.origins[lane], &packet.directions[lane]);
+                    closest[lane] = nearest(closest[lane].take(), hit);
+                }
+            }
+        })
+    }
+
+    // any_hit para un paquete de rayos de sombra; max_dist por carril
+    pub fn any_hit_packet(&self, packet: &RayPacket, max_dist: &[f32; LANES]) -> [bool; LANES] {
+        self.first_hit_packet(packet, max_dist).each_ref().map(Option::is_some)
+    }
+
+    // first_hit para un paquete de rayos de sombra
+    pub fn first_hit_packet(&self, packet: &RayPacket, max_dist: &[f32; LANES]) -> [Option<HitInfo>; LANES] {
+        let Some(tlas) = &self.tlas else {
+            return std::array::from_fn(|lane| {
+                packet.active[lane]
+                    .then(|| self.first_hit(&packet.origins[lane], &packet.directions[lane], max_dist[lane]))
+                    .flatten()
+            });
+        };
+        tlas.first_hit_packet_with(packet, max_dist, |blas, mask, blocked| match blas {
+            FIXED => {
+                let hits = first_hit_packet(&self.objects, self.bvh.as_ref(), &packet.masked(mask), max_dist);
+                // Los carriles fuera de `mask` vuelven sin impacto
+                for (blocked, hit) in blocked.iter_mut().zip(hits) {
+                    if hit.is_some() {
+                        *blocked = hit;
+                    }
+                }
+            }
+            cube => {
+                let cube = &self.dynamic[cube as usize - 1];
+                for lane in (0..LANES).filter(|&lane| mask[lane]) {
+                    let hit = cube.ray_intersect(&packet.origins[lane], &packet.directions[lane]);
+                    blocked[lane] = hit.filter(|hit| hit.distance < max_dist[lane]);
+                }
+            }
+        })
+    }
+
+    // Versiones sin BVH: prueban todos los objetos
+    pub fn closest_hit_linear(&self, origin: &Vector3, direction: &Vector3) -> Option<HitInfo> {
+        nearest(
+            closest_hit_linear(&self.objects, origin, direction),
+            closest_hit_linear(&self.dynamic, origin, direction),
+        )
+    }
+
+    pub fn any_hit_linear(&self, origin: &Vector3, direction: &Vector3, max_dist: f32) -> bool {
+        first_hit_linear(&self.objects, origin, direction, max_dist).is_some()
+            || first_hit_linear(&self.dynamic, origin, direction, max_dist).is_some()
     }
 
     pub fn as_slice(&self) -> Vec<&dyn RayIntersect> {
@@ -31,7 +336,7 @@ impl Scene {
         count_z: i32,
         spacing: f32,
         half_size: Vector3,
-        material: Material,
+        material: MaterialId,
     ) {
         for ix in 0..count_x {
             for iy in 0..count_y {
@@ -46,7 +351,8 @@ impl Scene {
                         half_size,
                         rot_x: 0.0,
                         rot_y: 0.0,
-                        material: material.clone(),
+                        material,
+                        uv_offset: Vector3::zero(),
                     });
                 }
             }
@@ -58,7 +364,7 @@ impl Scene {
         &mut self,
         center: Vector3,
         half_size: Vector3,
-        material: Material,
+        material: MaterialId,
     ) {
         self.add_cube(Cube {
             center,
@@ -66,6 +372,77 @@ impl Scene {
             rot_x: 0.0,
             rot_y: 0.0,
             material,
+            uv_offset: Vector3::zero(),
         });
     }
-}
\ No newline at end of file
+}
+
+// Consultas sobre el BLAS de los objetos fijos, con su BVH si ya se armó
+fn closest_hit<T: RayIntersect>(objects: &[T], bvh: Option<&Bvh>, origin: &Vector3, direction: &Vector3) -> Option<HitInfo> {
+    match bvh {
+        Some(bvh) => bvh.closest_hit(objects, origin, direction),
+        None => closest_hit_linear(objects, origin, direction),
+    }
+}
+
+fn first_hit<T: RayIntersect>(
+    objects: &[T],
+    bvh: Option<&Bvh>,
+    origin: &Vector3,
+    direction: &Vector3,
+    max_dist: f32,
+) -> Option<HitInfo> {
+    match bvh {
+        Some(bvh) => bvh.first_hit(objects, origin, direction, max_dist),
+        None => first_hit_linear(objects, origin, direction, max_dist),
+    }
+}
+
+fn closest_hit_packet<T: RayIntersect>(objects: &[T], bvh: Option<&Bvh>, packet: &RayPacket) -> [Option<HitInfo>; LANES] {
+    match bvh {
+        Some(bvh) => bvh.closest_hit_packet(objects, packet),
+        None => std::array::from_fn(|lane| {
+            if !packet.active[lane] {
+                return None;
+            }
+            closest_hit_linear(objects, &packet.origins[lane], &packet.directions[lane])
+        }),
+    }
+}
+
+fn first_hit_packet<T: RayIntersect>(
+    objects: &[T],
+    bvh: Option<&Bvh>,
+    packet: &RayPacket,
+    max_dist: &[f32; LANES],
+) -> [Option<HitInfo>; LANES] {
+    match bvh {
+        Some(bvh) => bvh.first_hit_packet(objects, packet, max_dist),
+        None => std::array::from_fn(|lane| {
+            if !packet.active[lane] {
+                return None;
+            }
+            first_hit_linear(objects, &packet.origins[lane], &packet.directions[lane], max_dist[lane])
+        }),
+    }
+}
+
+fn closest_hit_linear<T: RayIntersect>(objects: &[T], origin: &Vector3, direction: &Vector3) -> Option<HitInfo> {
+    let mut closest_hit: Option<HitInfo> = None;
+    for object in objects {
+        if let Some(hit) = object.ray_intersect(origin, direction) {
+            if closest_hit.as_ref().is_none_or(|c| hit.distance < c.distance) {
+                closest_hit = Some(hit);
+            }
+        }
+    }
+    closest_hit
+}
+
+fn first_hit_linear<T: RayIntersect>(objects: &[T], origin: &Vector3, direction: &Vector3, max_dist: f32) -> Option<HitInfo> {
+    objects.iter().find_map(|object| {
+        object
+            .ray_intersect(origin, direction)
+            .filter(|hit| hit.distance < max_dist)
+    })
+}
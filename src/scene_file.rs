@@ -0,0 +1,452 @@
+// scene_file.rs
+// Carga la escena (cubos, luces, cámara y skybox) desde un archivo JSON
+// en lugar de tenerla escrita a mano en main.rs.
+use raylib::prelude::*;
+use serde::Deserialize;
+use serde_json::value::RawValue;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::anvil::{self, AnvilOptions};
+use crate::camera::Camera;
+use crate::import::BLOCK_SIZE;
+use crate::instance::{Instance, Transform};
+use crate::cube::Cube;
+use crate::light::Light;
+use crate::materials::MaterialLibrary;
+use crate::optimize::{self, MergeReport};
+use crate::scene::{Scene, Spin, VoxelLayout};
+use crate::skybox::Skybox;
+use crate::schematic::{self, SchematicOptions};
+use crate::structure;
+use crate::vox::{self, VoxOptions};
+
+// Todo lo que describe un archivo de escena, ya convertido a los tipos del raytracer
+pub struct LoadedScene {
+    pub scene: Scene,
+    pub lights: Vec<Light>,
+    pub camera: Camera,
+    pub skybox: Skybox,
+    // Avisos no fatales (p. ej. bloques importados sin material)
+    pub warnings: Vec<String>,
+    // Archivos importados desde la escena (para recargarla si cambian)
+    pub sources: Vec<PathBuf>,
+    // Lo que juntó `merge` en la escena y los prototipos
+    pub merge: MergeReport,
+    // Cubos con `spin`: main los gira cada cuadro con Scene::animate
+    pub spins: Vec<Spin>,
+}
+
+// Error de carga con archivo, línea y la entrada que lo provocó
+#[derive(Debug)]
+pub struct SceneFileError {
+    pub path: PathBuf,
+    pub line: usize,
+    pub entry: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for SceneFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: ", self.path.display(), self.line)?;
+        if let Some(entry) = &self.entry {
+            write!(f, "{}: ", entry)?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for SceneFileError {}
+
+#[derive(Deserialize)]
+struct RawSceneFile<'a> {
+    camera: CameraEntry,
+    #[serde(default)]
+    skybox: Option<SkyboxEntry>,
+    // Las luces y cubos se guardan crudos para saber en qué línea empieza cada uno
+    #[serde(borrow, default)]
+    lights: Vec<&'a RawValue>,
+    #[serde(borrow, default)]
+    cubes: Vec<&'a RawValue>,
+    // Construcciones de Minecraft que se agregan a la escena
+    #[serde(borrow, default)]
+    imports: Vec<&'a RawValue>,
+    // Prototipos con nombre (cubos e importaciones) y las instancias que los ubican
+    #[serde(borrow, default)]
+    prototypes: Vec<&'a RawValue>,
+    #[serde(borrow, default)]
+    instances: Vec<&'a RawValue>,
+    // "grid" u "octree": los bloques alineados se juntan en un VoxelGrid o en un octree
+    #[serde(default)]
+    voxels: Option<String>,
+    // Junta los bloques vecinos del mismo material en cajas más grandes
+    #[serde(default)]
+    merge: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraEntry {
+    eye: [f32; 3],
+    center: [f32; 3],
+    #[serde(default = "default_up")]
+    up: [f32; 3],
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SkyboxEntry {
+    top: String,
+    bottom: String,
+    left: String,
+    right: String,
+    front: String,
+    back: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LightEntry {
+    position: [f32; 3],
+    #[serde(default = "default_light_color")]
+    color: [f32; 3],
+    intensity: f32,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CubeEntry {
+    center: [f32; 3],
+    half_size: [f32; 3],
+    #[serde(default)]
+    rot_x: f32,
+    #[serde(default)]
+    rot_y: f32,
+    material: String,
+    // Radianes por segundo en x e y: el cubo gira y va al BVH de los objetos dinámicos
+    #[serde(default)]
+    spin: Option<[f32; 2]>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ImportEntry {
+    file: String,
+    #[serde(default)]
+    origin: [f32; 3],
+    // Solo .schem: centra la construcción en el centro de órbita de la cámara
+    #[serde(default)]
+    recenter: bool,
+    // Solo .vox: índice de paleta -> nombre de material texturizado
+    #[serde(default)]
+    palette: HashMap<u8, String>,
+    // Solo mundos Anvil: caja en coordenadas de bloque del mundo
+    #[serde(default)]
+    min: Option<[i32; 3]>,
+    #[serde(default)]
+    max: Option<[i32; 3]>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PrototypeEntry<'a> {
+    name: String,
+    #[serde(borrow, default)]
+    cubes: Vec<&'a RawValue>,
+    #[serde(borrow, default)]
+    imports: Vec<&'a RawValue>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct InstanceEntry {
+    prototype: String,
+    #[serde(default)]
+    position: [f32; 3],
+    #[serde(default)]
+    rot_y: f32,
+    #[serde(default = "default_scale")]
+    scale: f32,
+}
+
+fn default_up() -> [f32; 3] {
+    [0.0, 1.0, 0.0]
+}
+
+fn default_light_color() -> [f32; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+fn vec3(v: [f32; 3]) -> Vector3 {
+    Vector3::new(v[0], v[1], v[2])
+}
+
+// Número de línea (desde 1) en el que empieza `part`, que debe ser un pedazo de `source`
+pub(crate) fn line_of(source: &str, part: &str) -> usize {
+    let offset = (part.as_ptr() as usize).saturating_sub(source.as_ptr() as usize);
+    source[..offset.min(source.len())].matches('\n').count() + 1
+}
+
+// Versión corta de la entrada para mostrarla en el mensaje de error
+fn snippet(raw: &str) -> String {
+    let compact: String = raw.split_whitespace().collect::<Vec<_>>().join(" ");
+    if compact.chars().count() > 80 {
+        format!("{}...", compact.chars().take(77).collect::<String>())
+    } else {
+        compact
+    }
+}
+
+// Mensaje de serde sin el " at line X column Y" (la línea ya va en SceneFileError)
+pub(crate) fn json_message(e: &serde_json::Error) -> String {
+    let message = e.to_string();
+    let suffix = format!(" at line {} column {}", e.line(), e.column());
+    message.strip_suffix(&suffix).unwrap_or(&message).to_string()
+}
+
+// Los materiales que generan los importadores (p. ej. los colores de un .vox) y las
+// texturas del skybox se agregan a `materials`
+pub fn load_scene(path: &Path, materials: &mut MaterialLibrary) -> Result<LoadedScene, SceneFileError> {
+    let source = std::fs::read_to_string(path).map_err(|e| SceneFileError {
+        path: path.to_path_buf(),
+        line: 0,
+        entry: None,
+        message: e.to_string(),
+    })?;
+    parse_scene(path, &source, materials)
+}
+
+// Lo que hace falta para ubicar los errores de una entrada en el archivo
+struct SceneSource<'s> {
+    path: &'s Path,
+    text: &'s str,
+    // Punto alrededor del que orbita la cámara (para `recenter`)
+    camera_center: Vector3,
+}
+
+impl SceneSource<'_> {
+    // Error dentro de una entrada: la línea de serde es relativa al inicio de la entrada
+    fn entry_error(&self, label: String, raw: &RawValue, line: usize, message: String) -> SceneFileError {
+        SceneFileError {
+            path: self.path.to_path_buf(),
+            line: line_of(self.text, raw.get()) + line.saturating_sub(1),
+            entry: Some(format!("{} {}", label, snippet(raw.get()))),
+            message,
+        }
+    }
+}
+
+pub fn parse_scene(path: &Path, source: &str, materials: &mut MaterialLibrary) -> Result<LoadedScene, SceneFileError> {
+    let raw: RawSceneFile = serde_json::from_str(source).map_err(|e| SceneFileError {
+        path: path.to_path_buf(),
+        line: e.line(),
+        entry: None,
+        message: json_message(&e),
+    })?;
+    let source = SceneSource {
+        path,
+        text: source,
+        camera_center: vec3(raw.camera.center),
+    };
+
+    let voxels = match raw.voxels.as_deref() {
+        None => None,
+        Some("grid") => Some(VoxelLayout::Grid),
+        Some("octree") => Some(VoxelLayout::Octree),
+        Some(other) => {
+            return Err(SceneFileError {
+                path: path.to_path_buf(),
+                line: 0,
+                entry: None,
+                message: format!("`voxels` desconocido \"{}\" (se esperaba \"grid\" u \"octree\")", other),
+            })
+        }
+    };
+    // Junta bloques y voxeliza (si se pidió) y arma el BVH de una escena o un prototipo ya completo
+    let mut merge = MergeReport::default();
+    let mut finish = |scene: &mut Scene, materials: &MaterialLibrary| {
+        if raw.merge {
+            merge.add(optimize::merge_blocks(scene, materials, BLOCK_SIZE));
+        }
+        if let Some(layout) = voxels {
+            scene.voxelize(BLOCK_SIZE, layout);
+        }
+        scene.build_bvh();
+    };
+
+    let mut lights = Vec::with_capacity(raw.lights.len());
+    for (i, entry) in raw.lights.iter().enumerate() {
+        let light: LightEntry = serde_json::from_str(entry.get())
+            .map_err(|e| source.entry_error(format!("lights[{}]", i), entry, e.line(), json_message(&e)))?;
+        lights.push(Light {
+            position: vec3(light.position),
+            color: vec3(light.color),
+            intensity: light.intensity,
+        });
+    }
+
+    let mut scene = Scene::new();
+    let mut warnings = Vec::new();
+    let mut sources = Vec::new();
+    let mut spins = Vec::new();
+    load_cubes(&source, "", &raw.cubes, materials, &mut scene, Some(&mut spins))?;
+    load_imports(&source, "", &raw.imports, materials, &mut scene, &mut warnings, &mut sources)?;
+
+    // Cada prototipo es una escena con su propio BVH que comparten todas sus instancias
+    let mut prototypes: HashMap<String, Arc<Scene>> = HashMap::new();
+    for (i, entry) in raw.prototypes.iter().enumerate() {
+        let label = format!("prototypes[{}]", i);
+        let prototype: PrototypeEntry = serde_json::from_str(entry.get())
+            .map_err(|e| source.entry_error(label.clone(), entry, e.line(), json_message(&e)))?;
+        if prototypes.contains_key(&prototype.name) {
+            let message = format!("prototipo repetido \"{}\"", prototype.name);
+            return Err(source.entry_error(label, entry, 1, message));
+        }
+        let prefix = format!("{}.", label);
+        let mut prototype_scene = Scene::new();
+        load_cubes(&source, &prefix, &prototype.cubes, materials, &mut prototype_scene, None)?;
+        load_imports(&source, &prefix, &prototype.imports, materials, &mut prototype_scene, &mut warnings, &mut sources)?;
+        finish(&mut prototype_scene, materials);
+        prototypes.insert(prototype.name, Arc::new(prototype_scene));
+    }
+
+    for (i, entry) in raw.instances.iter().enumerate() {
+        let label = format!("instances[{}]", i);
+        let instance: InstanceEntry = serde_json::from_str(entry.get())
+            .map_err(|e| source.entry_error(label.clone(), entry, e.line(), json_message(&e)))?;
+        let Some(prototype) = prototypes.get(&instance.prototype) else {
+            let message = format!("prototipo desconocido \"{}\"", instance.prototype);
+            return Err(source.entry_error(label, entry, 1, message));
+        };
+        if instance.scale <= 0.0 {
+            return Err(source.entry_error(label, entry, 1, "`scale` debe ser mayor que 0".to_string()));
+        }
+        let transform = Transform {
+            translation: vec3(instance.position),
+            rot_y: instance.rot_y,
+            scale: instance.scale,
+        };
+        scene.add_instance(Instance::new(Arc::clone(prototype), transform));
+    }
+
+    finish(&mut scene, materials);
+
+    let camera = Camera::new(vec3(raw.camera.eye), vec3(raw.camera.center), vec3(raw.camera.up));
+
+    let skybox = match raw.skybox {
+        Some(s) => Skybox::new(&s.top, &s.bottom, &s.left, &s.right, &s.front, &s.back, &mut materials.textures),
+        None => Skybox::new_simple_minecraft(&mut materials.textures),
+    };
+
+    Ok(LoadedScene { scene, lights, camera, skybox, warnings, sources, merge, spins })
+}
+
+// Agrega a `scene` los cubos de la lista; `prefix` ubica la lista en los errores. Los cubos
+// con `spin` se anotan en `spins` (los prototipos no tienen: sus instancias los comparten)
+fn load_cubes(
+    source: &SceneSource,
+    prefix: &str,
+    entries: &[&RawValue],
+    materials: &MaterialLibrary,
+    scene: &mut Scene,
+    mut spins: Option<&mut Vec<Spin>>,
+) -> Result<(), SceneFileError> {
+    for (i, entry) in entries.iter().enumerate() {
+        let label = format!("{}cubes[{}]", prefix, i);
+        let cube: CubeEntry = serde_json::from_str(entry.get())
+            .map_err(|e| source.entry_error(label.clone(), entry, e.line(), json_message(&e)))?;
+        let material = materials.id(&cube.material).ok_or_else(|| {
+            source.entry_error(label.clone(), entry, 1, format!("material desconocido \"{}\"", cube.material))
+        })?;
+        let built = Cube {
+            center: vec3(cube.center),
+            half_size: vec3(cube.half_size),
+            rot_x: cube.rot_x,
+            rot_y: cube.rot_y,
+            material,
+            uv_offset: Vector3::zero(),
+        };
+        match (cube.spin, spins.as_deref_mut()) {
+            (None, _) => scene.add_cube(built),
+            (Some(speed), Some(spins)) => spins.push(Spin {
+                cube: scene.add_dynamic_cube(built),
+                rot_x: cube.rot_x,
+                rot_y: cube.rot_y,
+                speed,
+            }),
+            (Some(_), None) => {
+                let message = "`spin` no se puede usar en un prototipo".to_string();
+                return Err(source.entry_error(label, entry, 1, message));
+            }
+        }
+    }
+    Ok(())
+}
+
+// Agrega a `scene` las construcciones importadas, junto con sus avisos y sus archivos
+fn load_imports(
+    source: &SceneSource,
+    prefix: &str,
+    entries: &[&RawValue],
+    materials: &mut MaterialLibrary,
+    scene: &mut Scene,
+    warnings: &mut Vec<String>,
+    sources: &mut Vec<PathBuf>,
+) -> Result<(), SceneFileError> {
+    for (i, entry) in entries.iter().enumerate() {
+        let label = format!("{}imports[{}]", prefix, i);
+        let import: ImportEntry = serde_json::from_str(entry.get())
+            .map_err(|e| source.entry_error(label.clone(), entry, e.line(), json_message(&e)))?;
+        let file = Path::new(&import.file);
+        let origin = vec3(import.origin);
+        let extension = if file.is_dir() {
+            // Una carpeta `region` de un mundo guardado
+            "mca"
+        } else {
+            file.extension().and_then(|e| e.to_str()).unwrap_or("")
+        };
+        let report = match extension {
+            "nbt" => structure::load_structure(file, materials, scene, origin),
+            "schem" => {
+                let options = SchematicOptions {
+                    offset: origin,
+                    recenter: import.recenter.then_some(source.camera_center),
+                };
+                schematic::load_schematic(file, materials, scene, &options)
+            }
+            "vox" => {
+                let options = VoxOptions {
+                    origin,
+                    remap: import.palette.clone(),
+                };
+                vox::load_vox(file, materials, scene, &options)
+            }
+            "mca" => {
+                let (Some(min), Some(max)) = (import.min, import.max) else {
+                    return Err(source.entry_error(label, entry, 1, "un mundo Anvil necesita `min` y `max`".to_string()));
+                };
+                let region_dir = if file.is_dir() { file } else { file.parent().unwrap_or(Path::new(".")) };
+                let options = AnvilOptions { min, max, origin };
+                anvil::load_region_box(region_dir, materials, scene, &options)
+            }
+            _ => {
+                return Err(source.entry_error(
+                    label,
+                    entry,
+                    1,
+                    format!("formato de importación no soportado \"{}\"", extension),
+                ))
+            }
+        }
+        .map_err(|e| source.entry_error(label.clone(), entry, 1, e.to_string()))?;
+        warnings.extend(report.warnings().into_iter().map(|w| format!("{}: {}", import.file, w)));
+        sources.push(file.to_path_buf());
+    }
+    Ok(())
+}
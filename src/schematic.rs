@@ -0,0 +1,152 @@
+// schematic.rs
+// Importa schematics de WorldEdit en formato Sponge v2/v3 (.schem).
+// v2: raíz `Schematic` con Width/Height/Length, `Palette` y `BlockData`.
+// v3: lo mismo pero la paleta y los datos van dentro de `Blocks`.
+use raylib::prelude::*;
+use std::path::Path;
+
+use crate::import::{self, ImportError, ImportReport, BLOCK_SIZE};
+use crate::materials::MaterialLibrary;
+use crate::nbt::{self, Tag};
+use crate::scene::Scene;
+
+pub struct SchematicOptions {
+    // Desplazamiento que se suma a todos los bloques
+    pub offset: Vector3,
+    // Si es Some, el centro de la construcción queda en ese punto (p. ej. camera.center)
+    pub recenter: Option<Vector3>,
+}
+
+impl Default for SchematicOptions {
+    fn default() -> Self {
+        SchematicOptions {
+            offset: Vector3::zero(),
+            recenter: None,
+        }
+    }
+}
+
+pub fn load_schematic(
+    path: &Path,
+    materials: &MaterialLibrary,
+    scene: &mut Scene,
+    options: &SchematicOptions,
+) -> Result<ImportReport, ImportError> {
+    let bytes = std::fs::read(path)?;
+    let (_, root) = nbt::read(&bytes)?;
+    import_schematic(&root, materials, scene, options)
+        .map_err(|e| ImportError(format!("{}: {}", path.display(), e)))
+}
+
+pub fn import_schematic(
+    root: &Tag,
+    materials: &MaterialLibrary,
+    scene: &mut Scene,
+    options: &SchematicOptions,
+) -> Result<ImportReport, ImportError> {
+    // En v3 todo viene dentro de un Compound `Schematic`; en v2 la raíz ya es el schematic
+    let schem = root.get("Schematic").unwrap_or(root);
+    let version = schem.get("Version").and_then(|v| v.as_i32()).unwrap_or(2);
+
+    // Width/Height/Length son Short sin signo
+    let dimension = |key: &str| {
+        schem
+            .get(key)
+            .and_then(|v| v.as_i64())
+            .map(|v| (v & 0xffff) as usize)
+            .ok_or_else(|| ImportError(format!("falta `{}`", key)))
+    };
+    let width = dimension("Width")?;
+    let height = dimension("Height")?;
+    let length = dimension("Length")?;
+
+    let (palette_tag, data) = if version >= 3 {
+        let blocks = schem
+            .get("Blocks")
+            .ok_or_else(|| ImportError("schematic v3 sin `Blocks`".to_string()))?;
+        (blocks.get("Palette"), blocks.get("Data"))
+    } else {
+        (schem.get("Palette"), schem.get("BlockData"))
+    };
+    let palette_tag = palette_tag
+        .and_then(|p| p.as_compound())
+        .ok_or_else(|| ImportError("el schematic no tiene paleta".to_string()))?;
+    let data = data
+        .and_then(|d| d.as_byte_array())
+        .ok_or_else(|| ImportError("el schematic no tiene datos de bloques".to_string()))?;
+
+    // La paleta es un mapa "id de bloque" -> índice; la invertimos
+    let mut palette: Vec<&str> = Vec::new();
+    for (name, index) in palette_tag {
+        let index = index
+            .as_i32()
+            .filter(|i| *i >= 0)
+            .ok_or_else(|| ImportError(format!("índice inválido para {}", name)))? as usize;
+        if palette.len() <= index {
+            palette.resize(index + 1, "minecraft:air");
+        }
+        palette[index] = name;
+    }
+
+    let indices = decode_varints(data)?;
+    let volume = width * height * length;
+    if indices.len() < volume {
+        return Err(ImportError(format!(
+            "se esperaban {} bloques y hay {}",
+            volume,
+            indices.len()
+        )));
+    }
+
+    // Origen de la construcción: centrada en `recenter` si se pidió
+    let mut origin = options.offset;
+    if let Some(target) = options.recenter {
+        let half_extent = Vector3::new(
+            (width as f32 - 1.0) * BLOCK_SIZE * 0.5,
+            (height as f32 - 1.0) * BLOCK_SIZE * 0.5,
+            (length as f32 - 1.0) * BLOCK_SIZE * 0.5,
+        );
+        origin = origin + target - half_extent;
+    }
+
+    let mut report = ImportReport::default();
+    for y in 0..height {
+        for z in 0..length {
+            for x in 0..width {
+                let state = indices[(y * length + z) * width + x] as usize;
+                let name = palette.get(state).copied().ok_or_else(|| {
+                    ImportError(format!("bloque ({}, {}, {}) fuera de la paleta: {}", x, y, z, state))
+                })?;
+                let center = import::block_center(origin, x as i32, y as i32, z as i32);
+                import::place_block(scene, materials, &mut report, name, center);
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+// Los índices de la paleta vienen como varints (7 bits por byte, el bit alto indica que sigue)
+fn decode_varints(data: &[i8]) -> Result<Vec<u32>, ImportError> {
+    let mut values = Vec::with_capacity(data.len());
+    let mut value: u32 = 0;
+    let mut shift = 0;
+    for &byte in data {
+        let byte = byte as u8;
+        value |= ((byte & 0x7f) as u32) << shift;
+        if byte & 0x80 == 0 {
+            values.push(value);
+            value = 0;
+            shift = 0;
+        } else {
+            shift += 7;
+            if shift > 28 {
+                return Err(ImportError("varint demasiado largo en los datos de bloques".to_string()));
+            }
+        }
+    }
+    if shift != 0 {
+        return Err(ImportError("los datos de bloques terminan a mitad de un varint".to_string()));
+    }
+    Ok(values)
+}
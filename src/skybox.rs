@@ -1,14 +1,15 @@
 use raylib::prelude::*;
-use crate::textures::TextureManager;
+use crate::material::TextureId;
+use crate::textures::{TextureManager, TextureSet};
 
 pub struct Skybox {
-    // Rutas a las 6 caras del cubo
-    pub top: String,
-    pub bottom: String,
-    pub left: String,
-    pub right: String,
-    pub front: String,
-    pub back: String,
+    // Texturas de las 6 caras del cubo
+    pub top: TextureId,
+    pub bottom: TextureId,
+    pub left: TextureId,
+    pub right: TextureId,
+    pub front: TextureId,
+    pub back: TextureId,
 }
 
 impl Skybox {
@@ -19,14 +20,15 @@ impl Skybox {
         right: &str,
         front: &str,
         back: &str,
+        textures: &mut TextureSet,
     ) -> Self {
         Skybox {
-            top: top.to_string(),
-            bottom: bottom.to_string(),
-            left: left.to_string(),
-            right: right.to_string(),
-            front: front.to_string(),
-            back: back.to_string(),
+            top: textures.intern(top),
+            bottom: textures.intern(bottom),
+            left: textures.intern(left),
+            right: textures.intern(right),
+            front: textures.intern(front),
+            back: textures.intern(back),
         }
     }
 
@@ -40,18 +42,18 @@ impl Skybox {
         let abs_y = d.y.abs();
         let abs_z = d.z.abs();
 
-        let (face_path, u, v) = if abs_y >= abs_x && abs_y >= abs_z {
+        let (face, u, v) = if abs_y >= abs_x && abs_y >= abs_z {
             // Cara superior o inferior
             if d.y > 0.0 {
                 // Top face
                 let u = (d.x / d.y + 1.0) * 0.5;
                 let v = (d.z / d.y + 1.0) * 0.5;
-                (&self.top, u, v)
+                (self.top, u, v)
             } else {
                 // Bottom face
                 let u = (d.x / -d.y + 1.0) * 0.5;
                 let v = (-d.z / -d.y + 1.0) * 0.5;
-                (&self.bottom, u, v)
+                (self.bottom, u, v)
             }
         } else if abs_x >= abs_z {
             // Cara izquierda o derecha
@@ -59,12 +61,12 @@ impl Skybox {
                 // Right face (+X)
                 let u = (-d.z / d.x + 1.0) * 0.5;
                 let v = (d.y / d.x + 1.0) * 0.5;
-                (&self.right, u, v)
+                (self.right, u, v)
             } else {
                 // Left face (-X)
                 let u = (d.z / -d.x + 1.0) * 0.5;
                 let v = (d.y / -d.x + 1.0) * 0.5;
-                (&self.left, u, v)
+                (self.left, u, v)
             }
         } else {
             // Cara frontal o trasera
@@ -72,12 +74,12 @@ impl Skybox {
                 // Back face (+Z)
                 let u = (d.x / d.z + 1.0) * 0.5;
                 let v = (d.y / d.z + 1.0) * 0.5;
-                (&self.back, u, v)
+                (self.back, u, v)
             } else {
                 // Front face (-Z)
                 let u = (-d.x / -d.z + 1.0) * 0.5;
                 let v = (d.y / -d.z + 1.0) * 0.5;
-                (&self.front, u, v)
+                (self.front, u, v)
             }
         };
 
@@ -86,20 +88,21 @@ impl Skybox {
         let v = v.clamp(0.0, 1.0);
 
         // Sample la textura
-        texture_manager.sample_uv(face_path, u, v)
+        texture_manager.sample_uv(face, u, v, 0.0)
     }
 
     /// Versión simple para Minecraft: un solo color para cielo, otro para tierra
-    pub fn new_simple_minecraft() -> Self {
+    pub fn new_simple_minecraft(textures: &mut TextureSet) -> Self {
         // Usa la misma textura para todas las caras
         // Puedes crear texturas simples de 1 pixel o degradados
-        Skybox {
-            top: "assets/cielo_top.png".to_string(),
-            bottom: "assets/cielo_bot.png".to_string(),
-            left: "assets/cielo1.png".to_string(),
-            right: "assets/cielo1.png".to_string(),
-            front: "assets/cielo2.png".to_string(),
-            back: "assets/cielo2.png".to_string(),
-        }
+        Skybox::new(
+            "assets/cielo_top.png",
+            "assets/cielo_bot.png",
+            "assets/cielo1.png",
+            "assets/cielo1.png",
+            "assets/cielo2.png",
+            "assets/cielo2.png",
+            textures,
+        )
     }
 }
\ No newline at end of file
@@ -0,0 +1,68 @@
+// structure.rs
+// Importa archivos .nbt de Structure Block (Minecraft Java) a la escena.
+// Formato: Compound raíz con `size`, `palette` (o `palettes`) y `blocks`,
+// donde cada bloque tiene `pos` [x, y, z] y `state` (índice en la paleta).
+use raylib::prelude::*;
+use std::path::Path;
+
+use crate::import::{self, ImportError, ImportReport};
+use crate::materials::MaterialLibrary;
+use crate::nbt::{self, Tag};
+use crate::scene::Scene;
+
+pub fn load_structure(
+    path: &Path,
+    materials: &MaterialLibrary,
+    scene: &mut Scene,
+    origin: Vector3,
+) -> Result<ImportReport, ImportError> {
+    let bytes = std::fs::read(path)?;
+    let (_, root) = nbt::read(&bytes)?;
+    import_structure(&root, materials, scene, origin)
+        .map_err(|e| ImportError(format!("{}: {}", path.display(), e)))
+}
+
+pub fn import_structure(
+    root: &Tag,
+    materials: &MaterialLibrary,
+    scene: &mut Scene,
+    origin: Vector3,
+) -> Result<ImportReport, ImportError> {
+    // Las estructuras con variantes aleatorias traen `palettes`; usamos la primera
+    let palette = root
+        .get("palette")
+        .or_else(|| root.get("palettes").and_then(|p| p.as_list()).and_then(|l| l.first()))
+        .and_then(|p| p.as_list())
+        .ok_or_else(|| ImportError("la estructura no tiene `palette`".to_string()))?;
+
+    let names: Vec<&str> = palette
+        .iter()
+        .map(|entry| entry.get("Name").and_then(|n| n.as_str()).unwrap_or("minecraft:air"))
+        .collect();
+
+    let blocks = root
+        .get("blocks")
+        .and_then(|b| b.as_list())
+        .ok_or_else(|| ImportError("la estructura no tiene `blocks`".to_string()))?;
+
+    let mut report = ImportReport::default();
+    for (i, block) in blocks.iter().enumerate() {
+        let pos = block
+            .get("pos")
+            .and_then(|p| p.as_int_vec())
+            .filter(|p| p.len() == 3)
+            .ok_or_else(|| ImportError(format!("blocks[{}] sin `pos` válido", i)))?;
+        let state = block
+            .get("state")
+            .and_then(|s| s.as_i32())
+            .ok_or_else(|| ImportError(format!("blocks[{}] sin `state`", i)))?;
+        let name = names
+            .get(state as usize)
+            .ok_or_else(|| ImportError(format!("blocks[{}]: state {} fuera de la paleta", i, state)))?;
+
+        let center = import::block_center(origin, pos[0], pos[1], pos[2]);
+        import::place_block(scene, materials, &mut report, name, center);
+    }
+
+    Ok(report)
+}
@@ -0,0 +1,461 @@
+// svo.rs
+// Octree disperso de vóxeles para mundos grandes: las regiones vacías (o llenas
+// de un mismo bloque) se guardan como un solo nodo, así el rayo salta el espacio
+// vacío por niveles y la memoria depende de la superficie y no del volumen.
+// Los nodos van en un arreglo plano y cada hoja guarda un índice a la paleta.
+use raylib::prelude::*;
+
+use crate::bvh::Aabb;
+use crate::cube::Cube;
+use crate::material::MaterialId;
+use crate::ray_intersect::{HitInfo, RayIntersect};
+use crate::voxel_grid::{aligned_cells, voxel_hit};
+
+// Con códigos Morton de 64 bits caben 21 bits por eje
+pub const MAX_DEPTH: u32 = 21;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Node {
+    Empty,
+    // Todo el nodo es del material palette[i]
+    Solid(u16),
+    // Sólo se guardan los hijos no vacíos (bits de `mask`), seguidos desde nodes[first];
+    // hijo = x | y << 1 | z << 2
+    Branch { first: u32, mask: u8 },
+}
+
+impl Node {
+    // Hijo `octant` de un Branch
+    fn child(&self, nodes: &[Node], octant: u32) -> Node {
+        match *self {
+            Node::Branch { first, mask } if mask & (1 << octant) != 0 => {
+                let before = (mask & ((1u8 << octant) - 1)).count_ones();
+                nodes[(first + before) as usize]
+            }
+            _ => Node::Empty,
+        }
+    }
+}
+
+pub struct SparseVoxelOctree {
+    // Esquina mínima en espacio mundo
+    pub origin: Vector3,
+    pub cell_size: f32,
+    // La raíz cubre 2^depth celdas por lado
+    depth: u32,
+    // Celdas ocupadas por eje (para la caja del BVH)
+    extent: [u32; 3],
+    // nodes[0] es la raíz
+    nodes: Vec<Node>,
+    palette: Vec<MaterialId>,
+}
+
+// Junta los bloques en cualquier orden y arma el octree al final
+pub struct OctreeBuilder {
+    origin: Vector3,
+    cell_size: f32,
+    palette: Vec<MaterialId>,
+    // (código Morton de la celda, índice en la paleta)
+    voxels: Vec<(u64, u16)>,
+}
+
+impl OctreeBuilder {
+    pub fn new(origin: Vector3, cell_size: f32) -> Self {
+        OctreeBuilder {
+            origin,
+            cell_size,
+            palette: Vec::new(),
+            voxels: Vec::new(),
+        }
+    }
+
+    fn palette_index(&mut self, material: MaterialId) -> u16 {
+        // Los bloques suelen venir seguidos del mismo material: se prueba primero el último
+        if let Some(&(_, last)) = self.voxels.last()
+            && self.palette[last as usize] == material
+        {
+            return last;
+        }
+        match self.palette.iter().position(|m| *m == material) {
+            Some(i) => i as u16,
+            None => {
+                self.palette.push(material);
+                (self.palette.len() - 1) as u16
+            }
+        }
+    }
+
+    // Si la celda ya tenía un bloque gana el último
+    pub fn set(&mut self, cell: [u32; 3], material: MaterialId) {
+        if cell.iter().any(|c| *c >= 1 << MAX_DEPTH) {
+            return;
+        }
+        let id = self.palette_index(material);
+        self.voxels.push((morton(cell), id));
+    }
+
+    // Igual que VoxelGrid::add_rectangle; las celdas antes de `origin` se ignoran
+    pub fn add_rectangle(&mut self, center: Vector3, half_size: Vector3, material: MaterialId) -> bool {
+        let Some((min, max)) = aligned_cells(self.origin, self.cell_size, center, half_size) else { return false };
+        let limit = 1i64 << MAX_DEPTH;
+        for z in min[2].max(0)..max[2].min(limit) {
+            for y in min[1].max(0)..max[1].min(limit) {
+                for x in min[0].max(0)..max[0].min(limit) {
+                    self.set([x as u32, y as u32, z as u32], material);
+                }
+            }
+        }
+        true
+    }
+
+    // Agrega un cubo sin rotación si está alineado a la grilla
+    pub fn add_cube(&mut self, cube: &Cube) -> bool {
+        cube.rot_x == 0.0 && cube.rot_y == 0.0 && self.add_rectangle(cube.center, cube.half_size, cube.material)
+    }
+
+    pub fn build(mut self) -> SparseVoxelOctree {
+        // Orden estable: entre celdas repetidas queda al final la última que se puso
+        self.voxels.sort_by_key(|v| v.0);
+        let mut voxels: Vec<(u64, u16)> = Vec::with_capacity(self.voxels.len());
+        for v in self.voxels {
+            match voxels.last_mut() {
+                Some(last) if last.0 == v.0 => *last = v,
+                _ => voxels.push(v),
+            }
+        }
+
+        let mut extent = [0u32; 3];
+        for &(code, _) in &voxels {
+            let cell = demorton(code);
+            for i in 0..3 {
+                extent[i] = extent[i].max(cell[i] + 1);
+            }
+        }
+        let largest = extent.into_iter().max().unwrap_or(0);
+        let depth = largest.next_power_of_two().trailing_zeros();
+
+        let mut nodes = vec![Node::Empty];
+        nodes[0] = build_node(&mut nodes, &voxels, depth);
+        SparseVoxelOctree {
+            origin: self.origin,
+            cell_size: self.cell_size,
+            depth,
+            extent,
+            nodes,
+            palette: self.palette,
+        }
+    }
+}
+
+// `voxels` está ordenado y todos caen en el mismo nodo de 2^level celdas por lado
+fn build_node(nodes: &mut Vec<Node>, voxels: &[(u64, u16)], level: u32) -> Node {
+    if voxels.is_empty() {
+        return Node::Empty;
+    }
+    if level == 0 {
+        return Node::Solid(voxels[0].1);
+    }
+
+    // Cada hijo recibe el tramo de `voxels` de su octante
+    let shift = 3 * (level - 1);
+    let mut ranges = [&voxels[..0]; 8];
+    let mut mask = 0u8;
+    let mut rest = voxels;
+    for (octant, range) in ranges.iter_mut().enumerate() {
+        let split = rest.partition_point(|v| (v.0 >> shift) & 7 <= octant as u64);
+        (*range, rest) = rest.split_at(split);
+        if !range.is_empty() {
+            mask |= 1 << octant;
+        }
+    }
+
+    let first = nodes.len();
+    nodes.extend(std::iter::repeat_n(Node::Empty, mask.count_ones() as usize));
+    let mut slot = first;
+    for range in ranges.iter().filter(|r| !r.is_empty()) {
+        nodes[slot] = build_node(nodes, range, level - 1);
+        slot += 1;
+    }
+
+    // Ocho hijos llenos del mismo material se juntan en una sola hoja; como ninguno
+    // tiene descendientes, se pueden sacar del arreglo
+    let children = &nodes[first..];
+    if let Node::Solid(id) = children[0]
+        && mask == 0xff
+        && children[..8].iter().all(|c| *c == Node::Solid(id))
+    {
+        nodes.truncate(first);
+        return Node::Solid(id);
+    }
+    Node::Branch { first: first as u32, mask }
+}
+
+// Intercala los bits: x en el bit 0, y en el 1, z en el 2
+fn morton(cell: [u32; 3]) -> u64 {
+    spread(cell[0]) | spread(cell[1]) << 1 | spread(cell[2]) << 2
+}
+
+fn spread(v: u32) -> u64 {
+    let mut x = v as u64 & 0x1f_ffff;
+    x = (x | x << 32) & 0x1f_0000_0000_ffff;
+    x = (x | x << 16) & 0x1f_0000_ff00_00ff;
+    x = (x | x << 8) & 0x100f_00f0_0f00_f00f;
+    x = (x | x << 4) & 0x10c3_0c30_c30c_30c3;
+    x = (x | x << 2) & 0x1249_2492_4924_9249;
+    x
+}
+
+fn demorton(code: u64) -> [u32; 3] {
+    [compact(code), compact(code >> 1), compact(code >> 2)]
+}
+
+fn compact(code: u64) -> u32 {
+    let mut x = code & 0x1249_2492_4924_9249;
+    x = (x | x >> 2) & 0x10c3_0c30_c30c_30c3;
+    x = (x | x >> 4) & 0x100f_00f0_0f00_f00f;
+    x = (x | x >> 8) & 0x1f_0000_ff00_00ff;
+    x = (x | x >> 16) & 0x1f_0000_0000_ffff;
+    x = (x | x >> 32) & 0x1f_ffff;
+    x as u32
+}
+
+// Rayo en unidades de celda, relativo a la esquina del octree (las distancias t
+// quedan iguales que en espacio mundo)
+struct Ray {
+    origin: [f32; 3],
+    dir: [f32; 3],
+    inv: [f32; 3],
+}
+
+// Impacto dentro de un nodo: distancia, material, celda y normal
+struct OctreeHit {
+    t: f32,
+    id: u16,
+    cell: [u32; 3],
+    normal: Vector3,
+}
+
+impl SparseVoxelOctree {
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    // Memoria aproximada de los nodos (sin la paleta)
+    pub fn node_bytes(&self) -> usize {
+        self.nodes.len() * std::mem::size_of::<Node>()
+    }
+
+    pub fn get(&self, cell: [u32; 3]) -> Option<MaterialId> {
+        if cell.iter().any(|c| *c >= 1 << self.depth) {
+            return None;
+        }
+        let mut node = self.nodes[0];
+        let mut level = self.depth;
+        loop {
+            match node {
+                Node::Empty => return None,
+                Node::Solid(id) => return Some(self.palette[id as usize]),
+                Node::Branch { .. } => {
+                    level -= 1;
+                    let octant = (cell[0] >> level & 1) | (cell[1] >> level & 1) << 1 | (cell[2] >> level & 1) << 2;
+                    node = node.child(&self.nodes, octant);
+                }
+            }
+        }
+    }
+
+    fn corner(&self, cell: [u32; 3]) -> [f32; 3] {
+        [
+            self.origin.x + cell[0] as f32 * self.cell_size,
+            self.origin.y + cell[1] as f32 * self.cell_size,
+            self.origin.z + cell[2] as f32 * self.cell_size,
+        ]
+    }
+
+    fn cell_center(&self, cell: [u32; 3]) -> Vector3 {
+        let c = self.corner(cell);
+        let half = self.cell_size * 0.5;
+        Vector3::new(c[0] + half, c[1] + half, c[2] + half)
+    }
+
+    // Recorre el nodo que empieza en la celda `min` y mide `size` celdas por lado.
+    // t0[i]..t1[i] es el tramo del rayo entre los planos del nodo en el eje i, así los
+    // tramos de los hijos salen de los planos del medio sin volver a probar cajas.
+    fn traverse(
+        &self,
+        node: Node,
+        min: [u32; 3],
+        size: u32,
+        t0: [f32; 3],
+        t1: [f32; 3],
+        ray: &Ray,
+    ) -> Option<OctreeHit> {
+        let t_enter = t0[0].max(t0[1]).max(t0[2]);
+        let t_exit = t1[0].min(t1[1]).min(t1[2]);
+        if t_enter > t_exit || t_exit < 0.0 {
+            return None;
+        }
+        match node {
+            Node::Empty => None,
+            Node::Solid(id) if t_enter >= 0.0 => {
+                // Entra por la cara `axis`: la celda es la del lado de adentro de esa cara
+                let axis = (0..3).max_by(|&a, &b| t0[a].total_cmp(&t0[b])).unwrap();
+                let cell = [0, 1, 2].map(|i| {
+                    if i == axis {
+                        if ray.dir[i] > 0.0 { min[i] } else { min[i] + size - 1 }
+                    } else {
+                        let p = ray.origin[i] + ray.dir[i] * t_enter;
+                        let c = p.floor() as i64;
+                        c.clamp(min[i] as i64, (min[i] + size - 1) as i64) as u32
+                    }
+                });
+                Some(OctreeHit { t: t_enter, id, cell, normal: axis_normal(axis, -ray.dir[axis].signum()) })
+            }
+            Node::Solid(id) => {
+                // El rayo empieza adentro: como Cube, la cara de salida de la celda del origen
+                let cell = [0, 1, 2].map(|i| {
+                    let c = ray.origin[i].floor() as i64;
+                    c.clamp(min[i] as i64, (min[i] + size - 1) as i64) as u32
+                });
+                let exit = |i: usize| {
+                    let bound = if ray.dir[i] > 0.0 { cell[i] + 1 } else { cell[i] };
+                    (bound as f32 - ray.origin[i]) * ray.inv[i]
+                };
+                let axis = (0..3).filter(|&i| ray.dir[i] != 0.0).min_by(|&a, &b| exit(a).total_cmp(&exit(b)))?;
+                Some(OctreeHit { t: exit(axis), id, cell, normal: axis_normal(axis, ray.dir[axis].signum()) })
+            }
+            Node::Branch { .. } => {
+                let half = size / 2;
+                // Por eje: dónde cruza el plano del medio y cuál mitad ve primero el rayo
+                let mut t_mid = [f32::INFINITY; 3];
+                let mut near = [0u32; 3];
+                for i in 0..3 {
+                    let mid = (min[i] + half) as f32;
+                    if ray.dir[i] != 0.0 {
+                        t_mid[i] = (mid - ray.origin[i]) * ray.inv[i];
+                        near[i] = (ray.dir[i] < 0.0) as u32;
+                    } else {
+                        // Paralelo al plano: sólo pasa por la mitad donde está el origen
+                        near[i] = (ray.origin[i] >= mid) as u32;
+                    }
+                }
+
+                // Primer hijo: los planos que el rayo ya cruzó antes de entrar al nodo.
+                // Después se cruza el plano más cercano que falte (a lo sumo 4 hijos).
+                let mut far = [0, 1, 2].map(|i| t_mid[i] < t_enter);
+                loop {
+                    let octant = (0..3).map(|i| ((far[i] as u32) ^ near[i]) << i).sum::<u32>();
+                    let child = node.child(&self.nodes, octant);
+                    if child != Node::Empty {
+                        let child_min = child_min(min, half, octant);
+                        let c0 = [0, 1, 2].map(|i| if far[i] { t_mid[i] } else { t0[i] });
+                        let c1 = [0, 1, 2].map(|i| if far[i] { t1[i] } else { t_mid[i] });
+                        if let Some(hit) = self.traverse(child, child_min, half, c0, c1, ray) {
+                            return Some(hit);
+                        }
+                    }
+                    let next = (0..3).filter(|&i| !far[i]).min_by(|&a, &b| t_mid[a].total_cmp(&t_mid[b]));
+                    match next {
+                        Some(i) if t_mid[i] <= t_exit => far[i] = true,
+                        _ => return None,
+                    }
+                }
+            }
+        }
+    }
+
+    // Cubos llenos del nodo, uno por celda
+    fn visit_cells(&self, node: Node, min: [u32; 3], size: u32, f: &mut dyn FnMut([u32; 3], MaterialId)) {
+        match node {
+            Node::Empty => {}
+            Node::Solid(id) => {
+                for z in min[2]..min[2] + size {
+                    for y in min[1]..min[1] + size {
+                        for x in min[0]..min[0] + size {
+                            f([x, y, z], self.palette[id as usize]);
+                        }
+                    }
+                }
+            }
+            Node::Branch { .. } => {
+                let half = size / 2;
+                for octant in 0..8 {
+                    let child = node.child(&self.nodes, octant);
+                    self.visit_cells(child, child_min(min, half, octant), half, f);
+                }
+            }
+        }
+    }
+
+    pub fn filled(&self) -> usize {
+        let mut count = 0;
+        self.visit_cells(self.nodes[0], [0; 3], 1 << self.depth, &mut |_, _| count += 1);
+        count
+    }
+}
+
+fn child_min(min: [u32; 3], half: u32, octant: u32) -> [u32; 3] {
+    [
+        min[0] + (octant & 1) * half,
+        min[1] + (octant >> 1 & 1) * half,
+        min[2] + (octant >> 2 & 1) * half,
+    ]
+}
+
+fn axis_normal(axis: usize, sign: f32) -> Vector3 {
+    match axis {
+        0 => Vector3::new(sign, 0.0, 0.0),
+        1 => Vector3::new(0.0, sign, 0.0),
+        _ => Vector3::new(0.0, 0.0, sign),
+    }
+}
+
+impl RayIntersect for SparseVoxelOctree {
+    fn ray_intersect(&self, ray_origin: &Vector3, ray_direction: &Vector3) -> Option<HitInfo> {
+        let origin = (*ray_origin - self.origin) * (1.0 / self.cell_size);
+        let dir = *ray_direction * (1.0 / self.cell_size);
+        let ray = Ray {
+            origin: [origin.x, origin.y, origin.z],
+            dir: [dir.x, dir.y, dir.z],
+            inv: [1.0 / dir.x, 1.0 / dir.y, 1.0 / dir.z],
+        };
+        // Tramos del rayo entre los planos de la raíz en cada eje
+        let size = 1u32 << self.depth;
+        let (lo, hi) = (0.0, size as f32);
+        let mut t0 = [f32::NEG_INFINITY; 3];
+        let mut t1 = [f32::INFINITY; 3];
+        for i in 0..3 {
+            if ray.dir[i] != 0.0 {
+                let a = (lo - ray.origin[i]) * ray.inv[i];
+                let b = (hi - ray.origin[i]) * ray.inv[i];
+                (t0[i], t1[i]) = (a.min(b), a.max(b));
+            } else if ray.origin[i] < lo || ray.origin[i] > hi {
+                return None;
+            }
+        }
+        let hit = self.traverse(self.nodes[0], [0; 3], size, t0, t1, &ray)?;
+        let point = *ray_origin + *ray_direction * hit.t;
+        let material = self.palette[hit.id as usize];
+        Some(voxel_hit(self.cell_center(hit.cell), self.cell_size, material, point, hit.normal, hit.t))
+    }
+
+    fn aabb(&self) -> Aabb {
+        let max = self.corner(self.extent);
+        Aabb { min: self.origin, max: Vector3::new(max[0], max[1], max[2]) }
+    }
+
+    // Para el exportador: un cubo por celda ocupada
+    fn for_each_cube(&self, f: &mut dyn FnMut(&Cube)) {
+        let half = self.cell_size * 0.5;
+        self.visit_cells(self.nodes[0], [0; 3], 1 << self.depth, &mut |cell, material| {
+            f(&Cube {
+                center: self.cell_center(cell),
+                half_size: Vector3::new(half, half, half),
+                rot_x: 0.0,
+                rot_y: 0.0,
+                material,
+                uv_offset: Vector3::zero(),
+            })
+        });
+    }
+}
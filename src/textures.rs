@@ -1,10 +1,139 @@
 use raylib::prelude::*;
+use serde::Deserialize;
 use std::collections::HashMap;
+use std::time::SystemTime;
+
+use crate::material::TextureId;
+
+// Las animaciones de Minecraft cuentan el tiempo en ticks
+const TICKS_PER_SECOND: f32 = 20.0;
 
 pub struct CpuTexture {
     pub width: i32,
     pub height: i32,
     pub pixels: Vec<Vector3>, // Normalized RGB values
+    // Alfa de cada píxel en 0..=1 (para los materiales con alpha test)
+    pub alpha: Vec<f32>,
+    // Si la imagen tiene al lado un .mcmeta con su animación
+    pub animation: Option<Animation>,
+}
+
+// Cuadros de una textura animada, uno debajo del otro (o en grilla, de izquierda a derecha)
+pub struct Animation {
+    pub frame_width: i32,
+    pub frame_height: i32,
+    // (cuadro, duración en ticks) en el orden en que se muestran
+    pub frames: Vec<(u32, u32)>,
+    // Mezclar cada cuadro con el siguiente a medida que pasa su tiempo
+    pub interpolate: bool,
+    total_ticks: u32,
+}
+
+// archivo.png.mcmeta de Minecraft
+#[derive(Deserialize)]
+struct McMeta {
+    animation: AnimationMeta,
+}
+
+#[derive(Deserialize)]
+struct AnimationMeta {
+    #[serde(default = "default_frametime")]
+    frametime: u32,
+    #[serde(default)]
+    frames: Option<Vec<FrameMeta>>,
+    #[serde(default)]
+    interpolate: bool,
+    #[serde(default)]
+    width: Option<i32>,
+    #[serde(default)]
+    height: Option<i32>,
+}
+
+// Un cuadro es su índice o {index, time} con su propia duración
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FrameMeta {
+    Index(u32),
+    Timed { index: u32, time: u32 },
+}
+
+fn default_frametime() -> u32 {
+    1
+}
+
+impl Animation {
+    // Animación de la imagen en `path` según su .mcmeta. Sin .mcmeta no es animada, aunque
+    // sea una tira más alta que ancha (como en Minecraft)
+    pub fn load(path: &str, width: i32, height: i32) -> Result<Option<Animation>, String> {
+        let meta_path = format!("{}.mcmeta", path);
+        let Ok(text) = std::fs::read_to_string(&meta_path) else {
+            return Ok(None);
+        };
+        let meta = serde_json::from_str::<McMeta>(&text).map_err(|e| format!("{}: {}", meta_path, e))?.animation;
+        Self::from_meta(meta, width, height).map(Some).map_err(|e| format!("{}: {}", meta_path, e))
+    }
+
+    fn from_meta(meta: AnimationMeta, width: i32, height: i32) -> Result<Animation, String> {
+        // Sin tamaño explícito los cuadros son cuadrados, del ancho de la imagen
+        let frame_width = meta.width.unwrap_or(width.min(height));
+        let frame_height = meta.height.unwrap_or(frame_width);
+        if frame_width <= 0 || frame_height <= 0 || width % frame_width != 0 || height % frame_height != 0 {
+            return Err(format!(
+                "los cuadros de {}x{} no dividen la imagen de {}x{}",
+                frame_width, frame_height, width, height
+            ));
+        }
+        let count = ((width / frame_width) * (height / frame_height)) as u32;
+        if meta.frametime == 0 {
+            return Err("`frametime` debe ser mayor que 0".to_string());
+        }
+
+        let frames: Vec<(u32, u32)> = match meta.frames {
+            Some(frames) => frames
+                .into_iter()
+                .map(|frame| match frame {
+                    FrameMeta::Index(index) => (index, meta.frametime),
+                    FrameMeta::Timed { index, time } => (index, time),
+                })
+                .collect(),
+            None => (0..count).map(|index| (index, meta.frametime)).collect(),
+        };
+        if frames.is_empty() {
+            return Err("`frames` no puede estar vacío".to_string());
+        }
+        if let Some(&(index, _)) = frames.iter().find(|(index, _)| *index >= count) {
+            return Err(format!("el cuadro {} no existe (hay {})", index, count));
+        }
+        if frames.iter().any(|(_, time)| *time == 0) {
+            return Err("la duración de un cuadro debe ser mayor que 0".to_string());
+        }
+
+        let total_ticks = frames.iter().map(|(_, time)| time).sum();
+        Ok(Animation { frame_width, frame_height, frames, interpolate: meta.interpolate, total_ticks })
+    }
+
+    // Cuadro que se ve a los `time` segundos, el que le sigue y cuánto se avanzó hacia él (0..1).
+    // Como en Minecraft, la imagen solo cambia de un tick al siguiente (también al interpolar)
+    fn frame_at(&self, time: f32) -> (u32, u32, f32) {
+        let ticks = (time.max(0.0) * TICKS_PER_SECOND) as u64 % self.total_ticks as u64;
+        let mut start = 0;
+        for (i, &(frame, duration)) in self.frames.iter().enumerate() {
+            let end = start + duration as u64;
+            if ticks < end {
+                let next = self.frames[(i + 1) % self.frames.len()].0;
+                return (frame, next, (ticks - start) as f32 / duration as f32);
+            }
+            start = end;
+        }
+        (self.frames[0].0, self.frames[0].0, 0.0)
+    }
+
+    // Lo que se ve a los `time` segundos: el cuadro y, si se interpola, cuánto se mezcla con
+    // el siguiente
+    fn shown_at(&self, time: f32) -> (u32, f32) {
+        let (frame, _, t) = self.frame_at(time);
+        (frame, if self.interpolate { t } else { 0.0 })
+    }
 }
 
 impl CpuTexture {
@@ -22,78 +151,194 @@ impl CpuTexture {
                 )
             })
             .collect();
+        let alpha = colors.iter().map(|c| c.a as f32 / 255.0).collect();
 
         CpuTexture {
             width: image.width,
             height: image.height,
             pixels,
+            alpha,
+            animation: None,
         }
     }
+
+    // Índice del píxel en (u,v) dentro de `frame` (el único cuadro si no es animada)
+    fn texel(&self, u: f32, v: f32, frame: u32) -> usize {
+        let (frame_width, frame_height, x0, y0) = match &self.animation {
+            Some(animation) => {
+                let columns = (self.width / animation.frame_width) as u32;
+                let x0 = (frame % columns) as i32 * animation.frame_width;
+                let y0 = (frame / columns) as i32 * animation.frame_height;
+                (animation.frame_width, animation.frame_height, x0, y0)
+            }
+            None => (self.width, self.height, 0, 0),
+        };
+        // mapear u,v en [0,1] a coordenadas de pixel
+        let tx = (u * (frame_width as f32 - 1.0)).clamp(0.0, frame_width as f32 - 1.0) as i32;
+        // v típicamente viene con origen en bottom o top; aquí asumimos v=0→bottom. Si tu atlas está invertido, cambia a (1.0-v).
+        let ty = ((1.0 - v) * (frame_height as f32 - 1.0)).clamp(0.0, frame_height as f32 - 1.0) as i32;
+        ((y0 + ty) * self.width + x0 + tx) as usize
+    }
+}
+
+// Rutas de las texturas que usa la escena; cada ruta recibe un TextureId una sola vez
+#[derive(Default)]
+pub struct TextureSet {
+    paths: Vec<String>,
+    ids: HashMap<String, TextureId>,
+}
+
+impl TextureSet {
+    pub fn intern(&mut self, path: &str) -> TextureId {
+        if let Some(&id) = self.ids.get(path) {
+            return id;
+        }
+        let id = TextureId(self.paths.len() as u32);
+        self.paths.push(path.to_string());
+        self.ids.insert(path.to_string(), id);
+        id
+    }
+
+    pub fn path(&self, id: TextureId) -> &str {
+        &self.paths[id.0 as usize]
+    }
+
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (TextureId, &str)> {
+        self.paths.iter().enumerate().map(|(i, path)| (TextureId(i as u32), path.as_str()))
+    }
+}
+
+struct LoadedTexture {
+    path: String,
+    // Fecha de modificación de la imagen y de su .mcmeta al cargarla, para saber si hay que
+    // recargarla
+    stamp: (Option<SystemTime>, Option<SystemTime>),
+    cpu: CpuTexture,
+    gpu: Option<Texture2D>, // GPU textures para rendering
 }
 
+// Texturas cargadas, indexadas por TextureId (sin buscar por nombre al muestrear)
+#[derive(Default)]
 pub struct TextureManager {
-    cpu_textures: HashMap<String, CpuTexture>,
-    textures: HashMap<String, Texture2D>, // GPU textures para rendering
+    textures: Vec<LoadedTexture>,
 }
 
 impl TextureManager {
     pub fn new() -> Self { Self::default() }
 
-    pub fn load_texture(
+    // Carga las texturas del set; las que ya estaban cargadas y no cambiaron se reutilizan
+    // aunque tengan otro id. Si alguna falla no se toca nada y se conservan las anteriores.
+    pub fn load_all(
         &mut self,
         rl: &mut RaylibHandle,
         thread: &RaylibThread,
-        path: &str,
-    ) {
-        if self.textures.contains_key(path) {
-            return;
+        set: &TextureSet,
+    ) -> Result<(), String> {
+        self.load(Some((rl, thread)), set)
+    }
+
+    // Igual que load_all pero sin subir nada a la GPU (no hace falta ventana), p. ej. para --bench
+    pub fn load_cpu(&mut self, set: &TextureSet) -> Result<(), String> {
+        self.load(None, set)
+    }
+
+    fn load(&mut self, mut gpu: Option<(&mut RaylibHandle, &RaylibThread)>, set: &TextureSet) -> Result<(), String> {
+        enum Slot {
+            Reused(usize),
+            Loaded(LoadedTexture),
         }
+        let by_path: HashMap<&str, usize> =
+            self.textures.iter().enumerate().map(|(i, t)| (t.path.as_str(), i)).collect();
 
-        // Ajusta según la API de tu versión de raylib-rs si load_image devuelve Result
-        let image = Image::load_image(path)
-            .unwrap_or_else(|_| panic!("Failed to load image {}", path));
-
-        let texture = rl
-            .load_texture_from_image(thread, &image)
-            .unwrap_or_else(|_| panic!("Failed to load texture {}", path));
-            // si tu API devuelve Result: .unwrap_or_else(...)
-
-        let cpu_texture = CpuTexture::from_image(&image);
-
-        self.cpu_textures.insert(path.to_string(), cpu_texture);
-        self.textures.insert(path.to_string(), texture);
-    }
-
-    /// Muestra un texel dado (u,v) en [0,1]
-    pub fn sample_uv(&self, path: &str, u: f32, v: f32) -> Vector3 {
-        if let Some(cpu_texture) = self.cpu_textures.get(path) {
-            // mapear u,v en [0,1] a coordenadas de pixel
-            let tx = (u * (cpu_texture.width as f32 - 1.0)).clamp(0.0, cpu_texture.width as f32 - 1.0) as i32;
-            // v típicamente viene con origen en bottom o top; aquí asumimos v=0→bottom. Si tu atlas está invertido, cambia a (1.0-v).
-            let ty = ((1.0 - v) * (cpu_texture.height as f32 - 1.0)).clamp(0.0, cpu_texture.height as f32 - 1.0) as i32;
-            let index = (ty * cpu_texture.width + tx) as usize;
-            if index < cpu_texture.pixels.len() {
-                cpu_texture.pixels[index]
-            } else {
-                Vector3::one()
+        let mut slots = Vec::with_capacity(set.len());
+        for (_, path) in set.iter() {
+            let modified_at = |path: &str| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+            let modified = (modified_at(path), modified_at(&format!("{}.mcmeta", path)));
+            let existing = by_path.get(path).filter(|&&i| {
+                let texture = &self.textures[i];
+                texture.stamp == modified && (texture.gpu.is_some() || gpu.is_none())
+            });
+            if let Some(&index) = existing {
+                slots.push(Slot::Reused(index));
+                continue;
             }
-        } else {
-            Vector3::one()
+
+            let image = Image::load_image(path)
+                .map_err(|e| format!("Failed to load image {}: {}", path, e))?;
+            let texture = match gpu.as_mut() {
+                Some((rl, thread)) => Some(
+                    rl.load_texture_from_image(thread, &image)
+                        .map_err(|e| format!("Failed to load texture {}: {}", path, e))?,
+                ),
+                None => None,
+            };
+            let animation = Animation::load(path, image.width, image.height)?;
+            slots.push(Slot::Loaded(LoadedTexture {
+                path: path.to_string(),
+                stamp: modified,
+                cpu: CpuTexture { animation, ..CpuTexture::from_image(&image) },
+                gpu: texture,
+            }));
         }
+
+        // Todo cargó bien: se arma el arreglo nuevo en el orden de los ids. Cada ruta
+        // tiene un solo id, así que cada textura vieja se reutiliza a lo sumo una vez.
+        let mut old: Vec<Option<LoadedTexture>> = std::mem::take(&mut self.textures).into_iter().map(Some).collect();
+        self.textures = slots
+            .into_iter()
+            .map(|slot| match slot {
+                Slot::Reused(index) => old[index].take().expect("textura reutilizada dos veces"),
+                Slot::Loaded(texture) => texture,
+            })
+            .collect();
+        Ok(())
     }
 
-    pub fn get_texture(&self, path: &str) -> Option<&Texture2D> {
-        self.textures.get(path)
+    /// Muestra un texel dado (u,v) en [0,1]; en las texturas animadas, del cuadro que
+    /// corresponde a los `time` segundos
+    pub fn sample_uv(&self, id: TextureId, u: f32, v: f32, time: f32) -> Vector3 {
+        let Some(texture) = self.textures.get(id.0 as usize) else {
+            return Vector3::one();
+        };
+        let cpu_texture = &texture.cpu;
+        let pixel = |frame| cpu_texture.pixels.get(cpu_texture.texel(u, v, frame)).copied().unwrap_or(Vector3::one());
+        match &cpu_texture.animation {
+            Some(animation) => {
+                let (frame, next, t) = animation.frame_at(time);
+                if animation.interpolate {
+                    pixel(frame) * (1.0 - t) + pixel(next) * t
+                } else {
+                    pixel(frame)
+                }
+            }
+            None => pixel(0),
+        }
     }
 
-    // mantiene default impl como antes
-}
+    // Alfa del texel en (u,v) a los `time` segundos; 1 (opaco) si la textura no está cargada
+    pub fn sample_alpha(&self, id: TextureId, u: f32, v: f32, time: f32) -> f32 {
+        let Some(texture) = self.textures.get(id.0 as usize) else {
+            return 1.0;
+        };
+        let cpu_texture = &texture.cpu;
+        let frame = cpu_texture.animation.as_ref().map_or(0, |animation| animation.frame_at(time).0);
+        cpu_texture.alpha.get(cpu_texture.texel(u, v, frame)).copied().unwrap_or(1.0)
+    }
 
-impl Default for TextureManager {
-    fn default() -> Self {
-        TextureManager {
-            cpu_textures: HashMap::new(),
-            textures: HashMap::new(),
-        }
+    // ¿Alguna textura animada se ve distinta a los `to` segundos que a los `from`? Si no, las
+    // muestras acumuladas siguen valiendo
+    pub fn animation_changed(&self, from: f32, to: f32) -> bool {
+        self.textures
+            .iter()
+            .filter_map(|texture| texture.cpu.animation.as_ref())
+            .any(|animation| animation.shown_at(from) != animation.shown_at(to))
+    }
+
+    pub fn get_texture(&self, id: TextureId) -> Option<&Texture2D> {
+        self.textures.get(id.0 as usize)?.gpu.as_ref()
     }
 }
@@ -0,0 +1,113 @@
+// tiles.rs
+// Reparte la imagen en tiles rectangulares para renderizarlos en paralelo. Cada tile
+// recibe slices disjuntos de las filas del framebuffer, así cada hilo de rayon escribe
+// sus píxeles directamente sin juntarlos en un Vec al final.
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TileOrder {
+    // Filas de tiles de arriba hacia abajo, de izquierda a derecha
+    Scanline,
+    // En anillos desde el centro de la imagen hacia afuera
+    Spiral,
+}
+
+impl TileOrder {
+    pub fn parse(name: &str) -> Option<TileOrder> {
+        match name {
+            "scanline" => Some(TileOrder::Scanline),
+            "spiral" => Some(TileOrder::Spiral),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TileConfig {
+    // Lado del tile en píxeles (los del borde derecho e inferior pueden ser más chicos)
+    pub size: usize,
+    pub order: TileOrder,
+}
+
+impl Default for TileConfig {
+    fn default() -> Self {
+        TileConfig { size: 32, order: TileOrder::Scanline }
+    }
+}
+
+pub struct Tile<'a, T> {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+    // Una fila del framebuffer por cada fila del tile
+    rows: Vec<&'a mut [T]>,
+}
+
+impl<T> Tile<'_, T> {
+    // Llama a `f(x, y, píxel)` con cada píxel del tile (x, y en coordenadas de la imagen)
+    pub fn for_each_pixel(&mut self, mut f: impl FnMut(usize, usize, &mut T)) {
+        for (dy, row) in self.rows.iter_mut().enumerate() {
+            for (dx, pixel) in row.iter_mut().enumerate() {
+                f(self.x + dx, self.y + dy, pixel);
+            }
+        }
+    }
+
+    // Como for_each_pixel pero de a `run` píxeles seguidos de una fila (el último de cada
+    // fila puede ser más corto): `f(x, y, píxeles)` con (x, y) el primero del tramo
+    pub fn for_each_run(&mut self, run: usize, mut f: impl FnMut(usize, usize, &mut [T])) {
+        for (dy, row) in self.rows.iter_mut().enumerate() {
+            for (i, pixels) in row.chunks_mut(run).enumerate() {
+                f(self.x + i * run, self.y + dy, pixels);
+            }
+        }
+    }
+}
+
+// Parte `pixels` (fila por fila, `width` x `height`) en tiles, ya en el orden pedido
+pub fn split<T>(pixels: &mut [T], width: usize, height: usize, config: TileConfig) -> Vec<Tile<'_, T>> {
+    let size = config.size.max(1);
+    let columns = width.div_ceil(size);
+    let mut tiles: Vec<Tile<T>> = Vec::with_capacity(columns * height.div_ceil(size));
+
+    // Cada banda de `size` filas es contigua; cada fila de la banda se corta en un
+    // pedazo por tile con split_at_mut
+    for (band, band_pixels) in pixels[..width * height].chunks_mut(width * size).enumerate() {
+        let first = tiles.len();
+        let band_height = band_pixels.len() / width;
+        for column in 0..columns {
+            let x = column * size;
+            tiles.push(Tile {
+                x,
+                y: band * size,
+                width: size.min(width - x),
+                height: band_height,
+                rows: Vec::with_capacity(band_height),
+            });
+        }
+        for row in band_pixels.chunks_mut(width) {
+            let mut rest = row;
+            for tile in &mut tiles[first..] {
+                let (left, right) = std::mem::take(&mut rest).split_at_mut(tile.width);
+                tile.rows.push(left);
+                rest = right;
+            }
+        }
+    }
+
+    if config.order == TileOrder::Spiral {
+        // Por anillo (distancia de Chebyshev al centro, en tiles) y dentro del anillo por ángulo
+        let key = |tile: &Tile<T>| {
+            let dx = (tile.x + tile.width / 2) as f32 - width as f32 * 0.5;
+            let dy = (tile.y + tile.height / 2) as f32 - height as f32 * 0.5;
+            let ring = (dx.abs().max(dy.abs()) / size as f32) as usize;
+            (ring, dy.atan2(dx))
+        };
+        tiles.sort_by(|a, b| {
+            let (ring_a, angle_a) = key(a);
+            let (ring_b, angle_b) = key(b);
+            ring_a.cmp(&ring_b).then(angle_a.total_cmp(&angle_b))
+        });
+    }
+    tiles
+}
@@ -0,0 +1,442 @@
+// vox.rs
+// Importa modelos de MagicaVoxel (.vox): chunks SIZE/XYZI (modelos), RGBA (paleta),
+// nTRN/nGRP/nSHP (grafo de escena con transformaciones) y MATL (materiales).
+// MagicaVoxel usa Z hacia arriba; aquí se convierte a Y hacia arriba.
+use raylib::prelude::*;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::import::{self, ImportError, ImportReport};
+use crate::material::{Material, MaterialId, Pbr};
+use crate::materials::MaterialLibrary;
+use crate::scene::Scene;
+
+pub struct VoxOptions {
+    pub origin: Vector3,
+    // Índice de paleta (1..=255) -> nombre de un material texturizado de la biblioteca
+    pub remap: HashMap<u8, String>,
+}
+
+impl Default for VoxOptions {
+    fn default() -> Self {
+        VoxOptions {
+            origin: Vector3::zero(),
+            remap: HashMap::new(),
+        }
+    }
+}
+
+struct VoxModel {
+    size: [i32; 3],
+    voxels: Vec<[u8; 4]>, // x, y, z, índice de color
+}
+
+// Propiedades de un chunk MATL que nos interesan
+#[derive(Default, Clone)]
+struct VoxMaterial {
+    kind: String,
+    emit: f32,
+    flux: f32,
+    metal: f32,
+    rough: f32,
+    trans: f32,
+    ior: f32,
+}
+
+enum Node {
+    Transform { child: i32, rotation: u8, translation: [i32; 3] },
+    Group { children: Vec<i32> },
+    Shape { models: Vec<i32> },
+}
+
+#[derive(Default)]
+struct VoxFile {
+    models: Vec<VoxModel>,
+    palette: Vec<[u8; 4]>,
+    materials: HashMap<u8, VoxMaterial>,
+    nodes: HashMap<i32, Node>,
+}
+
+pub fn load_vox(
+    path: &Path,
+    materials: &mut MaterialLibrary,
+    scene: &mut Scene,
+    options: &VoxOptions,
+) -> Result<ImportReport, ImportError> {
+    let bytes = std::fs::read(path)?;
+    import_vox(&bytes, materials, scene, options)
+        .map_err(|e| ImportError(format!("{}: {}", path.display(), e)))
+}
+
+pub fn import_vox(
+    bytes: &[u8],
+    materials: &mut MaterialLibrary,
+    scene: &mut Scene,
+    options: &VoxOptions,
+) -> Result<ImportReport, ImportError> {
+    let file = parse(bytes)?;
+
+    // Materiales remapeados a entradas de la biblioteca
+    let mut remapped: HashMap<u8, MaterialId> = HashMap::new();
+    for (index, name) in &options.remap {
+        let material = materials
+            .id(name)
+            .ok_or_else(|| ImportError(format!("material desconocido \"{}\" para el índice {}", name, index)))?;
+        remapped.insert(*index, material);
+    }
+
+    // Un material de color plano por índice de la paleta, agregado a la biblioteca al usarse
+    // por primera vez
+    let mut generated: HashMap<u8, MaterialId> = HashMap::new();
+    let mut report = ImportReport::default();
+
+    let mut place = |model: &VoxModel, rotation: u8, translation: [i32; 3], scene: &mut Scene| {
+        let pivot = [model.size[0] / 2, model.size[1] / 2, model.size[2] / 2];
+        for v in &model.voxels {
+            let local = [
+                v[0] as i32 - pivot[0],
+                v[1] as i32 - pivot[1],
+                v[2] as i32 - pivot[2],
+            ];
+            let p = rotate(rotation, local);
+            let (x, y, z) = (p[0] + translation[0], p[1] + translation[1], p[2] + translation[2]);
+            let index = v[3];
+
+            let material = match remapped.get(&index) {
+                Some(m) => *m,
+                None => *generated
+                    .entry(index)
+                    .or_insert_with(|| materials.add(palette_material(&file, index))),
+            };
+            // Z arriba -> Y arriba
+            let center = import::block_center(options.origin, x, z, -y);
+            import::add_block(scene, center, material);
+            report.placed += 1;
+        }
+    };
+
+    if file.nodes.contains_key(&0) {
+        walk(&file, 0, IDENTITY, [0, 0, 0], 0, &mut |model, rotation, translation| {
+            if let Some(m) = file.models.get(model as usize) {
+                place(m, rotation, translation, scene);
+            }
+        })?;
+    } else {
+        // Archivos viejos sin grafo de escena: todos los modelos en el origen
+        for model in &file.models {
+            place(model, IDENTITY, [0, 0, 0], scene);
+        }
+    }
+
+    Ok(report)
+}
+
+fn palette_material(file: &VoxFile, index: u8) -> Material {
+    let [r, g, b, a] = file.palette[index as usize];
+    let mut material = Material {
+        diffuse: Color::new(r, g, b, a),
+        specular: 10.0,
+        reflectivity: 0.0,
+        transparency: 0.0,
+        refractive_index: 1.0,
+        albedo: [0.9, 0.1],
+        textures: None,
+        emission: Vector3::zero(),
+        emission_strength: 0.0,
+        absorption: Vector3::zero(),
+        alpha_test: false,
+        pbr: None,
+    };
+
+    if let Some(matl) = file.materials.get(&index) {
+        match matl.kind.as_str() {
+            "_emit" => {
+                material.emission = Vector3::new(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0);
+                material.emission_strength = matl.emit * (1.0 + matl.flux);
+            }
+            "_metal" => {
+                material.reflectivity = matl.metal.clamp(0.0, 1.0);
+                material.specular = 120.0;
+                material.pbr = Some(Pbr {
+                    metallic: matl.metal.clamp(0.0, 1.0),
+                    roughness: matl.rough.clamp(0.0, 1.0),
+                    f0: 0.04,
+                });
+            }
+            "_glass" => {
+                material.transparency = matl.trans.clamp(0.0, 1.0);
+                // MagicaVoxel guarda el índice de refracción menos 1
+                material.refractive_index = 1.0 + matl.ior;
+            }
+            _ => {}
+        }
+    }
+    material
+}
+
+// Recorre el grafo nTRN -> nGRP/nSHP acumulando rotación y traslación
+fn walk(
+    file: &VoxFile,
+    id: i32,
+    rotation: u8,
+    translation: [i32; 3],
+    depth: usize,
+    visit: &mut dyn FnMut(i32, u8, [i32; 3]),
+) -> Result<(), ImportError> {
+    if depth > 64 {
+        return Err(ImportError("grafo de escena demasiado profundo".to_string()));
+    }
+    match file.nodes.get(&id) {
+        Some(Node::Transform { child, rotation: r, translation: t }) => {
+            // world = R_padre * (R * p + t) + t_padre
+            let t = rotate(rotation, *t);
+            let combined = [translation[0] + t[0], translation[1] + t[1], translation[2] + t[2]];
+            walk(file, *child, compose(rotation, *r), combined, depth + 1, visit)
+        }
+        Some(Node::Group { children }) => {
+            for child in children {
+                walk(file, *child, rotation, translation, depth + 1, visit)?;
+            }
+            Ok(())
+        }
+        Some(Node::Shape { models }) => {
+            for model in models {
+                visit(*model, rotation, translation);
+            }
+            Ok(())
+        }
+        None => Err(ImportError(format!("nodo {} no existe", id))),
+    }
+}
+
+// Rotación de nTRN empaquetada en un byte: bits 0-1 columna no nula de la fila 0,
+// bits 2-3 de la fila 1, bits 4/5/6 el signo de cada fila
+const IDENTITY: u8 = 0b0000_0100;
+
+fn rotation_matrix(r: u8) -> [[i32; 3]; 3] {
+    let c0 = (r & 3) as usize;
+    let c1 = ((r >> 2) & 3) as usize;
+    let c2 = 3usize.saturating_sub(c0 + c1);
+    let mut m = [[0; 3]; 3];
+    m[0][c0.min(2)] = if r & 0x10 != 0 { -1 } else { 1 };
+    m[1][c1.min(2)] = if r & 0x20 != 0 { -1 } else { 1 };
+    m[2][c2.min(2)] = if r & 0x40 != 0 { -1 } else { 1 };
+    m
+}
+
+fn encode_rotation(m: [[i32; 3]; 3]) -> u8 {
+    let column = |row: [i32; 3]| row.iter().position(|v| *v != 0).unwrap_or(0) as u8;
+    let mut r = column(m[0]) | (column(m[1]) << 2);
+    if m[0].iter().sum::<i32>() < 0 { r |= 0x10; }
+    if m[1].iter().sum::<i32>() < 0 { r |= 0x20; }
+    if m[2].iter().sum::<i32>() < 0 { r |= 0x40; }
+    r
+}
+
+fn rotate(r: u8, v: [i32; 3]) -> [i32; 3] {
+    let m = rotation_matrix(r);
+    [
+        m[0][0] * v[0] + m[0][1] * v[1] + m[0][2] * v[2],
+        m[1][0] * v[0] + m[1][1] * v[1] + m[1][2] * v[2],
+        m[2][0] * v[0] + m[2][1] * v[1] + m[2][2] * v[2],
+    ]
+}
+
+// Rotación equivalente a aplicar primero `inner` y luego `outer`
+fn compose(outer: u8, inner: u8) -> u8 {
+    let a = rotation_matrix(outer);
+    let b = rotation_matrix(inner);
+    let mut m = [[0; 3]; 3];
+    for (i, row) in m.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = (0..3).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    encode_rotation(m)
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], ImportError> {
+        if self.pos + n > self.data.len() {
+            return Err(ImportError(format!("fin de archivo inesperado en el byte {}", self.pos)));
+        }
+        let slice = &self.data[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(slice)
+    }
+
+    fn i32(&mut self) -> Result<i32, ImportError> {
+        Ok(i32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> Result<String, ImportError> {
+        let n = self.i32()?.max(0) as usize;
+        Ok(String::from_utf8_lossy(self.take(n)?).into_owned())
+    }
+
+    fn dict(&mut self) -> Result<HashMap<String, String>, ImportError> {
+        let n = self.i32()?.max(0);
+        let mut dict = HashMap::new();
+        for _ in 0..n {
+            let key = self.string()?;
+            let value = self.string()?;
+            dict.insert(key, value);
+        }
+        Ok(dict)
+    }
+}
+
+fn parse(bytes: &[u8]) -> Result<VoxFile, ImportError> {
+    let mut r = Reader { data: bytes, pos: 0 };
+    if r.take(4)? != b"VOX " {
+        return Err(ImportError("no es un archivo .vox".to_string()));
+    }
+    let _version = r.i32()?;
+    if r.take(4)? != b"MAIN" {
+        return Err(ImportError("falta el chunk MAIN".to_string()));
+    }
+    let main_content = r.i32()?.max(0) as usize;
+    let _main_children = r.i32()?;
+    r.take(main_content)?;
+
+    let mut file = VoxFile {
+        palette: default_palette(),
+        ..Default::default()
+    };
+    let mut pending_size: Option<[i32; 3]> = None;
+
+    while r.pos + 12 <= bytes.len() {
+        let id: [u8; 4] = r.take(4)?.try_into().unwrap();
+        let content_size = r.i32()?.max(0) as usize;
+        let children_size = r.i32()?.max(0) as usize;
+        let content = r.take(content_size)?;
+        let mut c = Reader { data: content, pos: 0 };
+
+        match &id {
+            b"SIZE" => {
+                pending_size = Some([c.i32()?, c.i32()?, c.i32()?]);
+            }
+            b"XYZI" => {
+                let size = pending_size
+                    .take()
+                    .ok_or_else(|| ImportError("XYZI sin SIZE previo".to_string()))?;
+                let n = c.i32()?.max(0) as usize;
+                let mut voxels = Vec::with_capacity(n);
+                for _ in 0..n {
+                    let v = c.take(4)?;
+                    voxels.push([v[0], v[1], v[2], v[3]]);
+                }
+                file.models.push(VoxModel { size, voxels });
+            }
+            b"RGBA" => {
+                // La entrada i del chunk corresponde al índice de color i + 1
+                for i in 0..255 {
+                    let rgba = c.take(4)?;
+                    file.palette[i + 1] = [rgba[0], rgba[1], rgba[2], rgba[3]];
+                }
+            }
+            b"nTRN" => {
+                let node = c.i32()?;
+                let _attributes = c.dict()?;
+                let child = c.i32()?;
+                let _reserved = c.i32()?;
+                let _layer = c.i32()?;
+                let frames = c.i32()?;
+                let mut rotation = IDENTITY;
+                let mut translation = [0; 3];
+                // Solo usamos el primer frame (sin animación)
+                for frame in 0..frames {
+                    let dict = c.dict()?;
+                    if frame != 0 {
+                        continue;
+                    }
+                    if let Some(value) = dict.get("_r") {
+                        rotation = value.trim().parse().unwrap_or(IDENTITY);
+                    }
+                    if let Some(value) = dict.get("_t") {
+                        let parts: Vec<i32> = value.split_whitespace().filter_map(|p| p.parse().ok()).collect();
+                        if parts.len() == 3 {
+                            translation = [parts[0], parts[1], parts[2]];
+                        }
+                    }
+                }
+                file.nodes.insert(node, Node::Transform { child, rotation, translation });
+            }
+            b"nGRP" => {
+                let node = c.i32()?;
+                let _attributes = c.dict()?;
+                let n = c.i32()?.max(0);
+                let mut children = Vec::new();
+                for _ in 0..n {
+                    children.push(c.i32()?);
+                }
+                file.nodes.insert(node, Node::Group { children });
+            }
+            b"nSHP" => {
+                let node = c.i32()?;
+                let _attributes = c.dict()?;
+                let n = c.i32()?.max(0);
+                let mut models = Vec::new();
+                for _ in 0..n {
+                    models.push(c.i32()?);
+                    let _model_attributes = c.dict()?;
+                }
+                file.nodes.insert(node, Node::Shape { models });
+            }
+            b"MATL" => {
+                let index = c.i32()?;
+                let dict = c.dict()?;
+                let number = |key: &str| dict.get(key).and_then(|v| v.parse::<f32>().ok()).unwrap_or(0.0);
+                if (1..=255).contains(&index) {
+                    file.materials.insert(
+                        index as u8,
+                        VoxMaterial {
+                            kind: dict.get("_type").cloned().unwrap_or_default(),
+                            emit: number("_emit"),
+                            flux: number("_flux"),
+                            metal: number("_metal"),
+                            rough: number("_rough"),
+                            trans: number("_trans"),
+                            ior: number("_ior"),
+                        },
+                    );
+                }
+            }
+            _ => {}
+        }
+
+        // Ningún chunk de hijos nos interesa fuera de MAIN
+        r.take(children_size)?;
+    }
+
+    Ok(file)
+}
+
+// Paleta por defecto de MagicaVoxel, usada cuando el archivo no trae RGBA
+fn default_palette() -> Vec<[u8; 4]> {
+    const DEFAULT: [u32; 256] = [
+        0x00000000, 0xffffffff, 0xffccffff, 0xff99ffff, 0xff66ffff, 0xff33ffff, 0xff00ffff, 0xffffccff, 0xffccccff, 0xff99ccff, 0xff66ccff, 0xff33ccff, 0xff00ccff, 0xffff99ff, 0xffcc99ff, 0xff9999ff,
+        0xff6699ff, 0xff3399ff, 0xff0099ff, 0xffff66ff, 0xffcc66ff, 0xff9966ff, 0xff6666ff, 0xff3366ff, 0xff0066ff, 0xffff33ff, 0xffcc33ff, 0xff9933ff, 0xff6633ff, 0xff3333ff, 0xff0033ff, 0xffff00ff,
+        0xffcc00ff, 0xff9900ff, 0xff6600ff, 0xff3300ff, 0xff0000ff, 0xffffffcc, 0xffccffcc, 0xff99ffcc, 0xff66ffcc, 0xff33ffcc, 0xff00ffcc, 0xffffcccc, 0xffcccccc, 0xff99cccc, 0xff66cccc, 0xff33cccc,
+        0xff00cccc, 0xffff99cc, 0xffcc99cc, 0xff9999cc, 0xff6699cc, 0xff3399cc, 0xff0099cc, 0xffff66cc, 0xffcc66cc, 0xff9966cc, 0xff6666cc, 0xff3366cc, 0xff0066cc, 0xffff33cc, 0xffcc33cc, 0xff9933cc,
+        0xff6633cc, 0xff3333cc, 0xff0033cc, 0xffff00cc, 0xffcc00cc, 0xff9900cc, 0xff6600cc, 0xff3300cc, 0xff0000cc, 0xffffff99, 0xffccff99, 0xff99ff99, 0xff66ff99, 0xff33ff99, 0xff00ff99, 0xffffcc99,
+        0xffcccc99, 0xff99cc99, 0xff66cc99, 0xff33cc99, 0xff00cc99, 0xffff9999, 0xffcc9999, 0xff999999, 0xff669999, 0xff339999, 0xff009999, 0xffff6699, 0xffcc6699, 0xff996699, 0xff666699, 0xff336699,
+        0xff006699, 0xffff3399, 0xffcc3399, 0xff993399, 0xff663399, 0xff333399, 0xff003399, 0xffff0099, 0xffcc0099, 0xff990099, 0xff660099, 0xff330099, 0xff000099, 0xffffff66, 0xffccff66, 0xff99ff66,
+        0xff66ff66, 0xff33ff66, 0xff00ff66, 0xffffcc66, 0xffcccc66, 0xff99cc66, 0xff66cc66, 0xff33cc66, 0xff00cc66, 0xffff9966, 0xffcc9966, 0xff999966, 0xff669966, 0xff339966, 0xff009966, 0xffff6666,
+        0xffcc6666, 0xff996666, 0xff666666, 0xff336666, 0xff006666, 0xffff3366, 0xffcc3366, 0xff993366, 0xff663366, 0xff333366, 0xff003366, 0xffff0066, 0xffcc0066, 0xff990066, 0xff660066, 0xff330066,
+        0xff000066, 0xffffff33, 0xffccff33, 0xff99ff33, 0xff66ff33, 0xff33ff33, 0xff00ff33, 0xffffcc33, 0xffcccc33, 0xff99cc33, 0xff66cc33, 0xff33cc33, 0xff00cc33, 0xffff9933, 0xffcc9933, 0xff999933,
+        0xff669933, 0xff339933, 0xff009933, 0xffff6633, 0xffcc6633, 0xff996633, 0xff666633, 0xff336633, 0xff006633, 0xffff3333, 0xffcc3333, 0xff993333, 0xff663333, 0xff333333, 0xff003333, 0xffff0033,
+        0xffcc0033, 0xff990033, 0xff660033, 0xff330033, 0xff000033, 0xffffff00, 0xffccff00, 0xff99ff00, 0xff66ff00, 0xff33ff00, 0xff00ff00, 0xffffcc00, 0xffcccc00, 0xff99cc00, 0xff66cc00, 0xff33cc00,
+        0xff00cc00, 0xffff9900, 0xffcc9900, 0xff999900, 0xff669900, 0xff339900, 0xff009900, 0xffff6600, 0xffcc6600, 0xff996600, 0xff666600, 0xff336600, 0xff006600, 0xffff3300, 0xffcc3300, 0xff993300,
+        0xff663300, 0xff333300, 0xff003300, 0xffff0000, 0xffcc0000, 0xff990000, 0xff660000, 0xff330000, 0xff0000ee, 0xff0000dd, 0xff0000bb, 0xff0000aa, 0xff000088, 0xff000077, 0xff000055, 0xff000044,
+        0xff000022, 0xff000011, 0xff00ee00, 0xff00dd00, 0xff00bb00, 0xff00aa00, 0xff008800, 0xff007700, 0xff005500, 0xff004400, 0xff002200, 0xff001100, 0xffee0000, 0xffdd0000, 0xffbb0000, 0xffaa0000,
+        0xff880000, 0xff770000, 0xff550000, 0xff440000, 0xff220000, 0xff110000, 0xffeeeeee, 0xffdddddd, 0xffbbbbbb, 0xffaaaaaa, 0xff888888, 0xff777777, 0xff555555, 0xff444444, 0xff222222, 0xff111111,
+    ];
+    // Los valores están en formato ABGR
+    DEFAULT.iter().map(|c| c.to_le_bytes()).collect()
+}
@@ -0,0 +1,314 @@
+// voxel_grid.rs
+// Grilla densa de bloques alineados a los ejes, recorrida con 3D-DDA
+// (Amanatides–Woo): el rayo avanza celda por celda en lugar de probar
+// cada cubo por separado. Cada celda guarda un índice a la paleta de materiales.
+use raylib::prelude::*;
+
+use crate::bvh::Aabb;
+use crate::cube::Cube;
+use crate::material::MaterialId;
+use crate::ray_intersect::{HitInfo, RayIntersect};
+
+pub struct VoxelGrid {
+    // Esquina mínima de la grilla en espacio mundo
+    pub origin: Vector3,
+    pub cell_size: f32,
+    pub dims: [usize; 3],
+    // 0 = vacío; si no, palette[celda - 1]
+    cells: Vec<u16>,
+    palette: Vec<MaterialId>,
+}
+
+impl VoxelGrid {
+    pub fn new(origin: Vector3, cell_size: f32, dims: [usize; 3]) -> Self {
+        VoxelGrid {
+            origin,
+            cell_size,
+            dims,
+            cells: vec![0; dims[0] * dims[1] * dims[2]],
+            palette: Vec::new(),
+        }
+    }
+
+    fn index(&self, x: usize, y: usize, z: usize) -> usize {
+        (z * self.dims[1] + y) * self.dims[0] + x
+    }
+
+    // Índice en la paleta para el material, agregándolo si no estaba
+    fn palette_index(&mut self, material: MaterialId) -> u16 {
+        match self.palette.iter().position(|m| *m == material) {
+            Some(i) => i as u16 + 1,
+            None => {
+                self.palette.push(material);
+                self.palette.len() as u16
+            }
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize, z: usize) -> Option<MaterialId> {
+        if x >= self.dims[0] || y >= self.dims[1] || z >= self.dims[2] {
+            return None;
+        }
+        match self.cells[self.index(x, y, z)] {
+            0 => None,
+            id => Some(self.palette[id as usize - 1]),
+        }
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, z: usize, material: Option<MaterialId>) {
+        if x >= self.dims[0] || y >= self.dims[1] || z >= self.dims[2] {
+            return;
+        }
+        let id = match material {
+            Some(material) => self.palette_index(material),
+            None => 0,
+        };
+        let index = self.index(x, y, z);
+        self.cells[index] = id;
+    }
+
+    // Celda que contiene el punto (en coordenadas enteras, puede quedar fuera de la grilla)
+    fn cell_of(&self, p: Vector3) -> [i64; 3] {
+        let local = (p - self.origin) * (1.0 / self.cell_size);
+        [local.x.floor() as i64, local.y.floor() as i64, local.z.floor() as i64]
+    }
+
+    fn cell_center(&self, cell: [i64; 3]) -> Vector3 {
+        self.origin
+            + Vector3::new(
+                (cell[0] as f32 + 0.5) * self.cell_size,
+                (cell[1] as f32 + 0.5) * self.cell_size,
+                (cell[2] as f32 + 0.5) * self.cell_size,
+            )
+    }
+
+    // Igual que Scene::add_rectangle: llena las celdas de la caja. Devuelve false si la
+    // caja no está alineada a la grilla (en ese caso no se agrega nada).
+    pub fn add_rectangle(&mut self, center: Vector3, half_size: Vector3, material: MaterialId) -> bool {
+        let Some((min, max)) = aligned_cells(self.origin, self.cell_size, center, half_size) else { return false };
+        let id = self.palette_index(material);
+        for z in min[2].max(0)..max[2].min(self.dims[2] as i64) {
+            for y in min[1].max(0)..max[1].min(self.dims[1] as i64) {
+                for x in min[0].max(0)..max[0].min(self.dims[0] as i64) {
+                    let index = self.index(x as usize, y as usize, z as usize);
+                    self.cells[index] = id;
+                }
+            }
+        }
+        true
+    }
+
+    // Igual que Scene::add_cube_grid
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_cube_grid(
+        &mut self,
+        start: Vector3,
+        count_x: i32,
+        count_y: i32,
+        count_z: i32,
+        spacing: f32,
+        half_size: Vector3,
+        material: MaterialId,
+    ) -> bool {
+        let mut all = true;
+        for ix in 0..count_x {
+            for iy in 0..count_y {
+                for iz in 0..count_z {
+                    let center = Vector3::new(
+                        start.x + ix as f32 * spacing,
+                        start.y + iy as f32 * spacing,
+                        start.z + iz as f32 * spacing,
+                    );
+                    all &= self.add_rectangle(center, half_size, material);
+                }
+            }
+        }
+        all
+    }
+
+    // Agrega un cubo sin rotación si está alineado a la grilla
+    pub fn add_cube(&mut self, cube: &Cube) -> bool {
+        cube.rot_x == 0.0 && cube.rot_y == 0.0 && self.add_rectangle(cube.center, cube.half_size, cube.material)
+    }
+
+    pub fn filled(&self) -> usize {
+        self.cells.iter().filter(|c| **c != 0).count()
+    }
+}
+
+// Celdas [min, max) que ocupa una caja, si está alineada a la grilla que empieza en `origin`
+pub fn aligned_cells(
+    origin: Vector3,
+    cell_size: f32,
+    center: Vector3,
+    half_size: Vector3,
+) -> Option<([i64; 3], [i64; 3])> {
+    let to_cells = |v: f32| {
+        let cells = v / cell_size;
+        ((cells - cells.round()).abs() < 1e-3).then(|| cells.round() as i64)
+    };
+    let min = center - half_size - origin;
+    let max = center + half_size - origin;
+    let min = [to_cells(min.x)?, to_cells(min.y)?, to_cells(min.z)?];
+    let max = [to_cells(max.x)?, to_cells(max.y)?, to_cells(max.z)?];
+    (0..3).all(|i| min[i] < max[i]).then_some((min, max))
+}
+
+// HitInfo para un bloque de la grilla (también lo usa el octree); la normal apunta hacia afuera
+pub fn voxel_hit(
+    cell_center: Vector3,
+    cell_size: f32,
+    material: MaterialId,
+    point: Vector3,
+    normal: Vector3,
+    distance: f32,
+) -> HitInfo {
+    // Punto local en [-1, 1] como un Cube con half_size 1, para que cast_ray ponga
+    // una textura por bloque
+    let local = (point - cell_center) * (2.0 / cell_size);
+    let local_point = Vector3::new(local.x.clamp(-1.0, 1.0), local.y.clamp(-1.0, 1.0), local.z.clamp(-1.0, 1.0));
+
+    HitInfo {
+        hit: true,
+        point,
+        local_point,
+        normal,
+        local_normal: normal,
+        distance,
+        material,
+    }
+}
+
+impl RayIntersect for VoxelGrid {
+    fn ray_intersect(&self, ray_origin: &Vector3, ray_direction: &Vector3) -> Option<HitInfo> {
+        let bounds = self.aabb();
+        let o = [ray_origin.x, ray_origin.y, ray_origin.z];
+        let d = [ray_direction.x, ray_direction.y, ray_direction.z];
+        let lo = [bounds.min.x, bounds.min.y, bounds.min.z];
+        let hi = [bounds.max.x, bounds.max.y, bounds.max.z];
+
+        // Entrada y salida de la caja de la grilla; `entry_axis` es la cara por la que entra
+        let mut t_enter = 0.0f32;
+        let mut t_exit = f32::INFINITY;
+        let mut entry_axis = None;
+        for i in 0..3 {
+            if d[i].abs() < 1e-12 {
+                if o[i] < lo[i] || o[i] > hi[i] {
+                    return None;
+                }
+                continue;
+            }
+            let t1 = (lo[i] - o[i]) / d[i];
+            let t2 = (hi[i] - o[i]) / d[i];
+            let (near, far) = (t1.min(t2), t1.max(t2));
+            if near > t_enter {
+                t_enter = near;
+                entry_axis = Some(i);
+            }
+            t_exit = t_exit.min(far);
+        }
+        if t_enter > t_exit {
+            return None;
+        }
+
+        // Celda inicial: la del punto de entrada (o la del origen si empieza adentro)
+        let start = *ray_origin + *ray_direction * t_enter;
+        let mut cell = self.cell_of(start);
+        for i in 0..3 {
+            cell[i] = cell[i].clamp(0, self.dims[i] as i64 - 1);
+        }
+        // Al entrar por una cara la celda es la del lado de adentro de esa cara
+        if let Some(axis) = entry_axis {
+            cell[axis] = if d[axis] > 0.0 { 0 } else { self.dims[axis] as i64 - 1 };
+        }
+
+        let step = [d[0].signum() as i64, d[1].signum() as i64, d[2].signum() as i64];
+        let mut t_max = [f32::INFINITY; 3];
+        let mut t_delta = [f32::INFINITY; 3];
+        for i in 0..3 {
+            if d[i].abs() < 1e-12 {
+                continue;
+            }
+            let boundary = lo[i] + (cell[i] + if step[i] > 0 { 1 } else { 0 }) as f32 * self.cell_size;
+            t_max[i] = (boundary - o[i]) / d[i];
+            t_delta[i] = self.cell_size / d[i].abs();
+        }
+
+        let axis_normal = |axis: usize, sign: f32| match axis {
+            0 => Vector3::new(sign, 0.0, 0.0),
+            1 => Vector3::new(0.0, sign, 0.0),
+            _ => Vector3::new(0.0, 0.0, sign),
+        };
+
+        // El rayo empieza dentro de un bloque: como Cube, devuelve la cara de salida de ese bloque
+        if entry_axis.is_none() {
+            if let Some(material) = self.get(cell[0] as usize, cell[1] as usize, cell[2] as usize) {
+                let axis = (0..3).min_by(|a, b| t_max[*a].total_cmp(&t_max[*b])).unwrap();
+                let t = t_max[axis];
+                let point = *ray_origin + *ray_direction * t;
+                return Some(voxel_hit(self.cell_center(cell), self.cell_size, material, point, axis_normal(axis, step[axis] as f32), t));
+            }
+        }
+
+        let mut t = t_enter;
+        let mut normal = match entry_axis {
+            Some(axis) => axis_normal(axis, -step[axis] as f32),
+            None => Vector3::zero(),
+        };
+        loop {
+            if let Some(material) = self.get(cell[0] as usize, cell[1] as usize, cell[2] as usize) {
+                let point = *ray_origin + *ray_direction * t;
+                return Some(voxel_hit(self.cell_center(cell), self.cell_size, material, point, normal, t));
+            }
+
+            // Avanza por el eje cuyo borde está más cerca
+            let axis = if t_max[0] < t_max[1] {
+                if t_max[0] < t_max[2] { 0 } else { 2 }
+            } else if t_max[1] < t_max[2] {
+                1
+            } else {
+                2
+            };
+            if t_max[axis] > t_exit {
+                return None;
+            }
+            cell[axis] += step[axis];
+            if cell[axis] < 0 || cell[axis] >= self.dims[axis] as i64 {
+                return None;
+            }
+            t = t_max[axis];
+            t_max[axis] += t_delta[axis];
+            normal = axis_normal(axis, -step[axis] as f32);
+        }
+    }
+
+    fn aabb(&self) -> Aabb {
+        let size = Vector3::new(
+            self.dims[0] as f32 * self.cell_size,
+            self.dims[1] as f32 * self.cell_size,
+            self.dims[2] as f32 * self.cell_size,
+        );
+        Aabb { min: self.origin, max: self.origin + size }
+    }
+
+    // Para el exportador: un cubo por celda ocupada
+    fn for_each_cube(&self, f: &mut dyn FnMut(&Cube)) {
+        let half = self.cell_size * 0.5;
+        for z in 0..self.dims[2] {
+            for y in 0..self.dims[1] {
+                for x in 0..self.dims[0] {
+                    if let Some(material) = self.get(x, y, z) {
+                        f(&Cube {
+                            center: self.cell_center([x as i64, y as i64, z as i64]),
+                            half_size: Vector3::new(half, half, half),
+                            rot_x: 0.0,
+                            rot_y: 0.0,
+                            material,
+                            uv_offset: Vector3::zero(),
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,64 @@
+// watcher.rs
+// Revisa cada cierto tiempo la fecha de modificación de archivos y carpetas
+// (sin hilos ni dependencias extra) para recargar la escena en caliente.
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+pub struct FileWatcher {
+    // Archivos o carpetas vigilados (las carpetas se recorren completas)
+    roots: Vec<PathBuf>,
+    stamps: HashMap<PathBuf, SystemTime>,
+    interval: Duration,
+    last_check: Instant,
+}
+
+impl FileWatcher {
+    pub fn new(roots: Vec<PathBuf>, interval: Duration) -> Self {
+        let stamps = scan(&roots);
+        FileWatcher {
+            roots,
+            stamps,
+            interval,
+            last_check: Instant::now(),
+        }
+    }
+
+    // Cambia lo que se vigila (p. ej. si la escena ahora importa otros archivos)
+    pub fn set_roots(&mut self, roots: Vec<PathBuf>) {
+        self.stamps = scan(&roots);
+        self.roots = roots;
+    }
+
+    // true si algo se creó, cambió o se borró desde la última revisión
+    pub fn poll(&mut self) -> bool {
+        if self.last_check.elapsed() < self.interval {
+            return false;
+        }
+        self.last_check = Instant::now();
+        let stamps = scan(&self.roots);
+        let changed = stamps != self.stamps;
+        self.stamps = stamps;
+        changed
+    }
+}
+
+fn scan(roots: &[PathBuf]) -> HashMap<PathBuf, SystemTime> {
+    let mut stamps = HashMap::new();
+    for root in roots {
+        visit(root, &mut stamps);
+    }
+    stamps
+}
+
+fn visit(path: &Path, stamps: &mut HashMap<PathBuf, SystemTime>) {
+    let Ok(metadata) = std::fs::metadata(path) else { return };
+    if metadata.is_dir() {
+        let Ok(entries) = std::fs::read_dir(path) else { return };
+        for entry in entries.flatten() {
+            visit(&entry.path(), stamps);
+        }
+    } else if let Ok(modified) = metadata.modified() {
+        stamps.insert(path.to_path_buf(), modified);
+    }
+}